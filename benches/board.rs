@@ -0,0 +1,182 @@
+//! Board collision and meshes, for boards from the default size up.
+//!
+//! Compares one cuboid collider per tile, as the board used to be built, with
+//! the merged compound collider from `board_collider`, both for building them
+//! and for the ray and shape casts the character controller does every frame.
+//!
+//! Run with `cargo bench --bench board`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use bevy::prelude::*;
+use bevy_rapier3d::rapier::na as nalgebra;
+use bevy_rapier3d::rapier::prelude::{
+    point, vector, Ball, ColliderBuilder, ColliderSet, Isometry, QueryFilter, QueryPipeline, Ray,
+    RigidBodySet,
+};
+
+#[allow(dead_code)]
+#[path = "../src/board/mesh.rs"]
+mod mesh;
+
+#[allow(dead_code)]
+#[path = "../src/board/noise.rs"]
+mod noise;
+
+use mesh::{board_collider, chunk_meshes, Column, COLUMN_FLOOR};
+use noise::terrain_height;
+
+const SIZES: [(usize, usize); 4] = [(14, 21), (64, 64), (128, 128), (256, 256)];
+const QUERIES: usize = 10_000;
+const SEED: u64 = 0x5EED;
+/// As in the default `BoardConfig` and `board`.
+const HEIGHT_AMPLITUDE: f32 = 0.15;
+const TILE_HALF_HEIGHT: f32 = 0.2;
+
+/// The generator's terrain, with the odd pit, like `generate_board` makes
+/// before placing features.
+fn columns(size_i: usize, size_j: usize) -> Vec<Vec<Option<Column>>> {
+    (0..size_j)
+        .map(|j| {
+            (0..size_i)
+                .map(|i| {
+                    ((i * 31 + j * 17) % 41 != 0).then(|| Column {
+                        top: terrain_height(SEED, HEIGHT_AMPLITUDE, i, j) + TILE_HALF_HEIGHT,
+                        top_color: Color::GREEN,
+                        side_color: Color::BEIGE,
+                        surface: 0,
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn per_tile(columns: &[Vec<Option<Column>>]) -> ColliderSet {
+    let mut colliders = ColliderSet::new();
+    for (j, row) in columns.iter().enumerate() {
+        for (i, column) in row.iter().enumerate() {
+            let Some(column) = column else {
+                continue;
+            };
+            let half_height = (column.top - COLUMN_FLOOR) / 2.0;
+            colliders.insert(
+                ColliderBuilder::cuboid(0.5, half_height, 0.5)
+                    .translation(vector![i as f32, COLUMN_FLOOR + half_height, j as f32])
+                    .build(),
+            );
+        }
+    }
+    colliders
+}
+
+fn merged(columns: &[Vec<Option<Column>>]) -> ColliderSet {
+    let mut colliders = ColliderSet::new();
+    if let Some(collider) = board_collider(columns) {
+        colliders.insert(ColliderBuilder::new(collider.raw).build());
+    }
+    colliders
+}
+
+/// A small xorshift, so both variants are queried at the same points.
+struct Points(u64);
+
+impl Points {
+    fn next(&mut self, max: usize) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % (max as u64 * 100)) as f32 / 100.0
+    }
+}
+
+type Build = fn(&[Vec<Option<Column>>]) -> ColliderSet;
+
+/// Time to build the colliders and query pipeline, and to run `QUERIES`
+/// downward rays plus as many sideways shape casts.
+fn run(columns: &[Vec<Option<Column>>], build: Build) -> (ColliderSet, Duration, Duration) {
+    let size = (columns[0].len(), columns.len());
+    let bodies = RigidBodySet::new();
+
+    let start = Instant::now();
+    let colliders = build(columns);
+    let mut pipeline = QueryPipeline::new();
+    pipeline.update(&bodies, &colliders);
+    let built = start.elapsed();
+
+    let ball = Ball::new(0.25);
+    let mut points = Points(0x2545_F491_4F6C_DD1D);
+    let start = Instant::now();
+    for _ in 0..QUERIES {
+        let (x, z) = (points.next(size.0), points.next(size.1));
+
+        let ray = Ray::new(point![x, 5.0, z], vector![0.0, -1.0, 0.0]);
+        black_box(pipeline.cast_ray(
+            &bodies,
+            &colliders,
+            &ray,
+            10.0,
+            true,
+            QueryFilter::default(),
+        ));
+
+        black_box(pipeline.cast_shape(
+            &bodies,
+            &colliders,
+            &Isometry::translation(x, 1.2, z),
+            &vector![0.5, 0.0, 0.3],
+            &ball,
+            1.0,
+            true,
+            QueryFilter::default(),
+        ));
+    }
+    let queried = start.elapsed();
+
+    (colliders, built, queried)
+}
+
+/// Boxes in the colliders, counting each part of a compound.
+fn shapes(colliders: &ColliderSet) -> usize {
+    colliders
+        .iter()
+        .map(|(_, collider)| {
+            collider
+                .shape()
+                .as_compound()
+                .map_or(1, |compound| compound.shapes().len())
+        })
+        .sum()
+}
+
+fn main() {
+    println!(
+        "{:>9}  {:<8} {:>9} {:>7} {:>10} {:>10}",
+        "board", "collider", "colliders", "shapes", "build", "queries"
+    );
+
+    for (size_i, size_j) in SIZES {
+        let columns = columns(size_i, size_j);
+        let board = format!("{size_i}x{size_j}");
+
+        let variants: [(&str, Build); 2] = [("per tile", per_tile), ("merged", merged)];
+        for (name, build) in variants {
+            let (colliders, built, queried) = run(&columns, build);
+            println!(
+                "{board:>9}  {name:<8} {:>9} {:>7} {built:>10.2?} {queried:>10.2?}",
+                colliders.len(),
+                shapes(&colliders),
+            );
+        }
+
+        let start = Instant::now();
+        let meshes = chunk_meshes(&columns);
+        println!(
+            "{board:>9}  {} chunk meshes built in {:.2?}, instead of {} tile scenes",
+            meshes.len(),
+            start.elapsed(),
+            size_i * size_j,
+        );
+    }
+}
@@ -21,8 +21,8 @@
 //!
 //! Meelee attacks have a short duration and zero velocity.
 //!
-//! Projectile attacks have a velocity and no duration. They will be
-//! destroyed when they have collided `n` times.
+//! Projectile attacks have a velocity, and a duration that only limits
+//! their range. They are destroyed at the first character or wall they hit.
 //!
 //!
 //! --
@@ -34,19 +34,30 @@
 //!
 //! * Damage
 //! * Conditions
-//! * Knockback
+//! * Knockback, in a fixed direction or away from the attack
 //! * Animation / model / effect / sound
 //!
 //! --
 //!
 //! Conditions is a Vector of conditions that are applied to hit entities.
 
-use crate::{ConditionType, Faction};
+use crate::{
+    actions::conditions::{Condition, ConditionTrait, Locked, Unstoppable},
+    agent::Health,
+    ConditionType, Faction, FactionRelations,
+};
+use bevy::ecs::query::Has;
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use core::time::Duration;
 use std::collections::HashSet;
 
+/// How long `ConditionType::Locked` stuns whatever it hits.
+const HIT_STUN: Duration = Duration::from_millis(300);
+
+/// How quickly knockback dies off, per second.
+const KNOCKBACK_DECAY: f32 = 8.0;
+
 #[derive(Component, Clone, Default)]
 pub struct LifeSpan {
     pub timer: Timer,
@@ -87,6 +98,186 @@ pub struct Attack {
     pub hit_set: HashSet<Entity>,
     pub velocity: Option<Velocity>,
     pub faction: Faction,
+    /// Whoever made the attack, if anyone.
+    pub source: Option<Entity>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HitKind {
+    Normal,
+    Critical,
+    /// Negative damage.
+    Heal,
+    /// A tick from a condition rather than a direct hit.
+    DamageOverTime,
+}
+
+/// Sent when something with `Health` takes damage or is healed.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct Hit {
+    pub target: Entity,
+    /// Whoever dealt the damage. `None` for the board, conditions and such.
+    pub attacker: Option<Entity>,
+    pub damage: f32,
+    pub position: Vec3,
+    pub kind: HitKind,
+}
+
+impl Attack {
+    /// Apply attacks to every enemy their hitbox overlaps, once per enemy.
+    pub fn hit_system(
+        mut commands: Commands,
+        rapier_context: Res<RapierContext>,
+        relations: Res<FactionRelations>,
+        mut attacks: Query<(&mut Attack, &Collider, &Transform)>,
+        mut targets: Query<(
+            &mut Health,
+            &Faction,
+            &Transform,
+            Option<&mut Condition<Locked>>,
+            Has<Unstoppable>,
+        )>,
+        mut hits: EventWriter<Hit>,
+    ) {
+        for (mut attack, hit_box, transform) in &mut attacks {
+            let mut touching = vec![];
+            rapier_context.intersections_with_shape(
+                transform.translation,
+                transform.rotation,
+                hit_box,
+                QueryFilter::default().exclude_sensors(),
+                |entity| {
+                    touching.push(entity);
+                    true
+                },
+            );
+
+            for entity in touching {
+                if attack.hit_set.contains(&entity) {
+                    continue;
+                }
+
+                if attack.source == Some(entity) {
+                    continue;
+                }
+
+                let Ok((mut health, faction, target_transform, mut locked, unstoppable)) =
+                    targets.get_mut(entity)
+                else {
+                    continue;
+                };
+
+                if !relations.can_hurt(attack.faction, *faction) {
+                    continue;
+                }
+
+                attack.hit_set.insert(entity);
+                health.current -= attack.effect.damage;
+
+                for condition in &attack.effect.conditions {
+                    match condition {
+                        ConditionType::Locked if unstoppable => {}
+                        ConditionType::Locked => {
+                            if let Some(locked) = locked.as_mut() {
+                                locked.add_timer(HIT_STUN);
+                            } else {
+                                commands
+                                    .entity(entity)
+                                    .insert(Condition::<Locked>::new(HIT_STUN));
+                            }
+                        }
+                    }
+                }
+
+                let away = Vec3::new(
+                    target_transform.translation.x - transform.translation.x,
+                    0.0,
+                    target_transform.translation.z - transform.translation.z,
+                )
+                .normalize_or_zero();
+                let knockback = attack.effect.knockback + away * attack.effect.push;
+                if knockback != Vec3::ZERO {
+                    commands.entity(entity).insert(Knockback(knockback));
+                }
+
+                let kind = if attack.effect.damage < 0.0 {
+                    HitKind::Heal
+                } else if attack.effect.critical {
+                    HitKind::Critical
+                } else {
+                    HitKind::Normal
+                };
+
+                hits.send(Hit {
+                    target: entity,
+                    attacker: attack.source,
+                    damage: attack.effect.damage,
+                    position: target_transform.translation,
+                    kind,
+                });
+            }
+        }
+    }
+}
+
+impl Attack {
+    /// Move attacks that have a velocity, and stop them at the first
+    /// character or wall they hit.
+    pub fn projectile_system(
+        mut commands: Commands,
+        rapier_context: Res<RapierContext>,
+        mut attacks: Query<(Entity, &Attack, &Collider, &mut Transform)>,
+        time: Res<Time>,
+    ) {
+        for (entity, attack, hit_box, mut transform) in &mut attacks {
+            let Some(velocity) = attack.velocity else {
+                continue;
+            };
+
+            if !attack.hit_set.is_empty() {
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+
+            transform.translation += velocity.linvel * time.delta_seconds();
+
+            let wall = rapier_context.intersection_with_shape(
+                transform.translation,
+                transform.rotation,
+                hit_box,
+                QueryFilter::only_fixed().exclude_sensors(),
+            );
+            if wall.is_some() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+/// Velocity pushing a character away from a hit. Decays over time.
+#[derive(Component, Clone, Copy)]
+#[component(storage = "SparseSet")]
+pub struct Knockback(pub Vec3);
+
+impl Knockback {
+    pub fn system(
+        mut commands: Commands,
+        mut query: Query<(Entity, &mut Knockback, &mut KinematicCharacterController)>,
+        time: Res<Time>,
+    ) {
+        let delta = time.delta_seconds();
+
+        for (entity, mut knockback, mut controller) in &mut query {
+            let mut translation = controller.translation.unwrap_or_default();
+            translation += knockback.0 * delta;
+            controller.translation = Some(translation);
+
+            knockback.0 *= (-KNOCKBACK_DECAY * delta).exp();
+            if knockback.0.length() < 0.1 {
+                commands.entity(entity).remove::<Knockback>();
+            }
+        }
+    }
 }
 
 #[derive(Bundle, Clone, Default)]
@@ -149,6 +340,7 @@ impl AttackBundle {
                 hit_set: HashSet::new(),
                 velocity: None,
                 faction,
+                source: None,
             },
             hit_box,
             sensor: Sensor,
@@ -157,6 +349,20 @@ impl AttackBundle {
             global_transform: GlobalTransform::default(),
         }
     }
+
+    /// Make the attack fly at `velocity` until it hits something.
+    #[must_use]
+    pub fn with_velocity(mut self, velocity: Vec3) -> Self {
+        self.attack.velocity = Some(Velocity::linear(velocity));
+        self
+    }
+
+    /// Credit hits to `source`.
+    #[must_use]
+    pub fn with_source(mut self, source: Entity) -> Self {
+        self.attack.source = Some(source);
+        self
+    }
 }
 
 #[derive(Component, Clone)]
@@ -164,6 +370,9 @@ pub struct Effect {
     pub damage: f32,
     pub conditions: Vec<ConditionType>,
     pub knockback: Vec3,
+    /// Knockback away from the middle of the attack, on top of `knockback`.
+    pub push: f32,
+    pub critical: bool,
 }
 
 impl Effect {
@@ -172,8 +381,17 @@ impl Effect {
             damage,
             conditions,
             knockback,
+            push: 0.0,
+            critical: false,
         }
     }
+
+    /// Also knock whatever is hit away from the middle of the attack.
+    #[must_use]
+    pub fn with_push(mut self, push: f32) -> Self {
+        self.push = push;
+        self
+    }
 }
 
 impl Default for Effect {
@@ -182,6 +400,8 @@ impl Default for Effect {
             damage: 0.0,
             conditions: vec![],
             knockback: Vec3::ZERO,
+            push: 0.0,
+            critical: false,
         }
     }
 }
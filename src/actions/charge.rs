@@ -0,0 +1,90 @@
+//! Held attacks.
+//!
+//! Holding `Action::Attack` past `CHARGE_THRESHOLD` starts charging a heavy
+//! attack. The attack is released when the button is, and its damage, hitbox
+//! size and knockback scale with how long the button was held, up to
+//! `CHARGE_MAX`. A tap shorter than the threshold is a regular attack.
+
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::*;
+use std::time::Duration;
+
+use super::Action;
+
+pub const CHARGE_THRESHOLD: Duration = Duration::from_millis(300);
+pub const CHARGE_MAX: Duration = Duration::from_millis(1500);
+
+/// Multipliers applied to a fully charged attack.
+pub const CHARGE_DAMAGE: f32 = 3.0;
+pub const CHARGE_HIT_BOX: f32 = 1.6;
+pub const CHARGE_KNOCKBACK: f32 = 6.0;
+
+/// Marks an entity that is holding a charged attack.
+#[derive(Component)]
+#[component(storage = "SparseSet")]
+pub struct Charging;
+
+/// How charged an attack held for `held` is, from 0.0 (a tap) to 1.0 (full).
+pub fn charge_ratio(held: Duration) -> f32 {
+    if held < CHARGE_THRESHOLD {
+        return 0.0;
+    }
+
+    let charged = held.saturating_sub(CHARGE_THRESHOLD).as_secs_f32();
+    let window = CHARGE_MAX.saturating_sub(CHARGE_THRESHOLD).as_secs_f32();
+    (charged / window).min(1.0)
+}
+
+impl Charging {
+    /// Draws a ring under charging entities that grows and reddens with charge.
+    pub fn indicator_system(
+        mut commands: Commands,
+        query: Query<(Entity, &ActionState<Action>, &Transform), With<Charging>>,
+        mut gizmos: Gizmos,
+    ) {
+        for (entity, action_state, transform) in &query {
+            // The release may have been swallowed, e.g. while locked.
+            if !action_state.pressed(Action::Attack) {
+                commands.entity(entity).remove::<Charging>();
+                continue;
+            }
+
+            let charge = charge_ratio(action_state.current_duration(Action::Attack));
+            let position = transform.translation + Vec3::Y * 0.05;
+            let color = Color::rgb(1.0, 1.0 - charge, 0.0);
+
+            gizmos.circle(position, Vec3::Y, 0.3 + 0.5 * charge, color);
+            if charge >= 1.0 {
+                gizmos.circle(position, Vec3::Y, 0.9, Color::WHITE);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_tap_is_not_charged() {
+        assert!(charge_ratio(Duration::ZERO).abs() < f32::EPSILON);
+        assert!(
+            charge_ratio(CHARGE_THRESHOLD.saturating_sub(Duration::from_millis(1))).abs()
+                < f32::EPSILON
+        );
+    }
+
+    #[test]
+    fn charge_grows_from_the_threshold() {
+        assert!(charge_ratio(CHARGE_THRESHOLD).abs() < f32::EPSILON);
+
+        let half = CHARGE_THRESHOLD + CHARGE_MAX.saturating_sub(CHARGE_THRESHOLD) / 2;
+        assert!((charge_ratio(half) - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn charge_stops_at_full() {
+        assert!((charge_ratio(CHARGE_MAX) - 1.0).abs() < f32::EPSILON);
+        assert!((charge_ratio(CHARGE_MAX * 4) - 1.0).abs() < f32::EPSILON);
+    }
+}
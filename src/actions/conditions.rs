@@ -16,6 +16,11 @@ pub trait ConditionTrait {
     fn add_timer(&mut self, duration: Duration);
 }
 
+/// Display name of a condition, e.g. for the HUD.
+pub trait ConditionName {
+    const NAME: &'static str;
+}
+
 /// Base trait for all conditions.
 /// Conditions are components that indicate that an entity is in a certain state.
 /// This trait counts the number of times the condition is applied.
@@ -40,6 +45,36 @@ impl<T> Condition<T> {
         }
     }
 
+    /// Time left until the longest-running source of this condition expires.
+    pub fn remaining(&self) -> Duration {
+        self.timers
+            .iter()
+            .map(Timer::remaining)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Make the condition last at least `duration` from now without adding
+    /// a source, e.g. for as long as something keeps causing it.
+    pub fn refresh(&mut self, duration: Duration) {
+        if self.remaining() >= duration {
+            return;
+        }
+
+        let longest = self
+            .timers
+            .iter_mut()
+            .filter(|timer| !timer.finished())
+            .max_by_key(|timer| timer.remaining());
+        match longest {
+            Some(timer) => {
+                timer.set_duration(duration);
+                timer.reset();
+            }
+            None => self.add_timer(duration),
+        }
+    }
+
     pub fn system(mut commands: Commands, mut query: Query<(Entity, &mut T)>, time: Res<Time>)
     where
         T: ConditionTrait + Component,
@@ -77,6 +112,28 @@ impl<T> ConditionTrait for Condition<T> {
 
 pub struct Locked;
 
+impl ConditionName for Locked {
+    const NAME: &'static str = "Locked";
+}
+
+/// Immune to `Locked`, e.g. a boss in its later phases.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Unstoppable;
+
+/// Moving at reduced speed, e.g. while wading through water.
+pub struct Slowed;
+
+impl ConditionName for Slowed {
+    const NAME: &'static str = "Slowed";
+}
+
+/// Taking damage over time, e.g. after stepping on lava.
+pub struct Burning;
+
+impl ConditionName for Burning {
+    const NAME: &'static str = "Burning";
+}
+
 #[derive(Clone)]
 pub enum ConditionType {
     Locked,
@@ -7,12 +7,18 @@ use std::{f32::consts::PI, time::Duration};
 use crate::animation::ActiveAnimation;
 
 pub mod conditions;
-use conditions::{Condition, Locked};
+use conditions::{Condition, Locked, Slowed};
 
 pub mod attack;
 pub use attack::*;
 
-use crate::Faction;
+pub mod charge;
+pub use charge::*;
+
+use crate::{board::Ground, Faction};
+
+/// Movement speed multiplier while `Slowed`.
+const SLOWED_SPEED: f32 = 0.5;
 
 #[derive(Actionlike, PartialEq, Clone, Copy, Debug, Reflect)]
 pub enum Action {
@@ -25,6 +31,10 @@ pub enum Action {
     Attack,
 }
 
+/// Movement carried over from the last frame, so characters can slide on ice.
+#[derive(Default, Component)]
+pub struct Momentum(pub Vec3);
+
 #[derive(Default, Component)]
 pub struct JumpState {
     available: bool,
@@ -32,6 +42,12 @@ pub struct JumpState {
     i: f32,
 }
 
+impl JumpState {
+    pub fn available(&self) -> bool {
+        self.available
+    }
+}
+
 impl From<f32> for JumpState {
     fn from(strength: f32) -> JumpState {
         JumpState {
@@ -78,7 +94,8 @@ pub fn jump(
             jump_state.available = false;
         }
 
-        if output.grounded {
+        // Only write on a real change, so the HUD can tell when it did.
+        if output.grounded && !jump_state.available {
             jump_state.available = true;
         }
 
@@ -125,15 +142,25 @@ pub fn move_system(
             &mut KinematicCharacterController,
             &mut Transform,
             Option<&mut ActiveAnimation>,
+            Option<&Ground>,
+            Option<&mut Momentum>,
+            Option<&Condition<Slowed>>,
         ),
         Without<Condition<Locked>>,
     >,
     time_step: Res<Time>,
 ) {
     let delta = time_step.delta_seconds() * 10.;
-    let speed = 0.5 * delta;
 
-    for (action_state, mut controller, mut transform, active_animation) in &mut agent_query {
+    for (action_state, mut controller, mut transform, active_animation, ground, momentum, slowed) in
+        &mut agent_query
+    {
+        let speed = if slowed.is_some() {
+            0.5 * delta * SLOWED_SPEED
+        } else {
+            0.5 * delta
+        };
+
         let mut translation_delta = Vec3::ZERO;
 
         let rotation = Quat::from_rotation_y(-PI * 1.75);
@@ -160,7 +187,15 @@ pub fn move_system(
             }
         }
 
-        translation_delta = translation_delta.normalize_or_zero() * speed;
+        // Keep analog input, e.g. a mob slowing down as it arrives.
+        translation_delta = translation_delta.clamp_length_max(1.0) * speed;
+
+        // On slippery ground, movement only gradually follows the input.
+        if let Some(mut momentum) = momentum {
+            let friction = ground.map_or(1.0, |Ground(kind)| kind.friction() * delta);
+            momentum.0 = momentum.0.lerp(translation_delta, friction.min(1.0));
+            translation_delta = momentum.0;
+        }
 
         let mut translation = controller.translation.unwrap_or_default();
         translation += translation_delta;
@@ -197,47 +232,80 @@ pub fn attack_system(
             Entity,
             &ActionState<Action>,
             &mut Transform,
+            &Faction,
             Option<&mut ActiveAnimation>,
+            Option<&Charging>,
         ),
         (Without<Condition<Locked>>, Without<PendingAttack>),
     >,
 ) {
-    for (entity, action_state, mut transform, active_animation) in &mut agent_query {
+    for (entity, action_state, mut transform, faction, active_animation, charging) in
+        &mut agent_query
+    {
+        let data = action_state.action_data(Action::Attack);
+        let axis = data.axis_pair.unwrap_or_default();
+        let direction = Vec3::new(axis.x(), 0., axis.y()).normalize_or_zero();
+
         if action_state.pressed(Action::Attack) {
-            let data = action_state.action_data(Action::Attack);
-            let axis = data.axis_pair.unwrap_or_default();
-            let direction = Vec3::new(axis.x(), 0., axis.y()).normalize_or_zero();
-
-            transform.rotation = crate::utils::look_to(direction);
-
-            if let Some(mut active_animation) = active_animation {
-                let attack = active_animation.animations.attack.clone_weak();
-                let idle = active_animation.animations.idle.clone_weak();
-                active_animation.set(attack).then(idle);
-
-                commands
-                    .entity(entity)
-                    .insert(Condition::<Locked>::new(Duration::from_millis(600)));
-
-                let attack_distance = 1.0;
-                let attack_transform = *transform
-                    * Transform::from_translation(-Vec3::Z * attack_distance + Vec3::Y * 0.75);
-
-                let attack_delay = Duration::from_millis(200);
-
-                let pending_attack = PendingAttack::new(
-                    AttackBundle::new(
-                        Effect::new(1.0, vec![], Vec3::ZERO),
-                        Collider::cuboid(0.5, 0.5, 0.5),
-                        Faction(0),
-                        Duration::from_millis(100),
-                        attack_transform,
-                    ),
-                    attack_delay,
-                );
-
-                commands.get_entity(entity).unwrap().insert(pending_attack);
+            let held = action_state.current_duration(Action::Attack);
+            if charging.is_none() && held >= CHARGE_THRESHOLD {
+                transform.rotation = crate::utils::look_to(direction);
+
+                if let Some(mut active_animation) = active_animation {
+                    let charge = active_animation.animations.charge.clone_weak();
+                    active_animation.set(charge);
+                }
+
+                commands.entity(entity).insert(Charging);
             }
+            continue;
+        }
+
+        if !action_state.just_released(Action::Attack) {
+            continue;
+        }
+
+        commands.entity(entity).remove::<Charging>();
+
+        let charge = charge_ratio(action_state.previous_duration(Action::Attack));
+
+        transform.rotation = crate::utils::look_to(direction);
+
+        if let Some(mut active_animation) = active_animation {
+            let attack = active_animation.animations.attack.clone_weak();
+            let idle = active_animation.animations.idle.clone_weak();
+            active_animation.set(attack).then(idle);
+
+            commands
+                .entity(entity)
+                .insert(Condition::<Locked>::new(Duration::from_millis(600)));
+
+            let attack_distance = 1.0 + 0.5 * charge;
+            let attack_transform = *transform
+                * Transform::from_translation(-Vec3::Z * attack_distance + Vec3::Y * 0.75);
+
+            let attack_delay = Duration::from_millis(200);
+
+            let damage = 1.0 + (CHARGE_DAMAGE - 1.0) * charge;
+            let hit_box = 0.5 * (1.0 + (CHARGE_HIT_BOX - 1.0) * charge);
+            let knockback = direction * CHARGE_KNOCKBACK * charge;
+
+            let mut effect = Effect::new(damage, vec![], knockback);
+            effect.critical = charge >= 1.0;
+
+            let pending_attack = PendingAttack::new(
+                AttackBundle::new(
+                    effect,
+                    Collider::cuboid(hit_box, 0.5, hit_box),
+                    *faction,
+                    Duration::from_millis(100),
+                    attack_transform,
+                )
+                .with_source(entity),
+                attack_delay,
+            );
+
+            commands.get_entity(entity).unwrap().insert(pending_attack);
         }
     }
 }
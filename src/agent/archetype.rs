@@ -0,0 +1,532 @@
+//! Kinds of mobs, defined in asset files.
+//!
+//! Every file in `MOB_ARCHETYPES` is loaded while the game loads and
+//! registered in `MobRegistry` under its `name`. Anything that wants a mob
+//! sends `SpawnMob` with the archetype's name, e.g. the level's mob spawns,
+//! whose `kind` is the archetype name.
+//!
+//! ```ron
+//! (
+//!     name: "enemy",
+//!     model: "models/world/enemy.glb#Scene0",
+//!     collider: Capsule(radius: 0.25, height: 1.05),
+//!     health: 3.0,
+//!     faction: 1,
+//!     attacks: [Melee((damage: 1.0, reach: 1.2))],
+//!     thinker: [
+//!         (when: Fear, then: Flee),
+//!         (when: Alert, then: Chase, weight: 0.9),
+//!     ],
+//! )
+//! ```
+//!
+//! The thinker picks the highest scoring choice, each score multiplied by its
+//! weight, and wanders when nothing scores. Weights above 1.0 only help
+//! scores that aren't already at the top. `perception`, `targeting`,
+//! `courage` and `steering` can be given to tune the mob, and default to the
+//! components' defaults. So do the fields of each attack.
+//!
+//! Mobs with `coordination` take turns attacking a target with their allies
+//! and circle it while they wait, see `squad`. A mob with a `boss` section
+//! is a boss, which goes through phases as it gets hurt. See `boss` for its
+//! phases and its own attacks.
+
+use std::time::Duration;
+
+use bevy::asset::{AssetLoader, LoadContext, LoadState, LoadedAsset};
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::{BoxedFuture, HashMap};
+use bevy_rapier3d::prelude::*;
+use big_brain::prelude::*;
+use iyes_progress::prelude::AssetsLoading;
+use leafwing_input_manager::InputManagerBundle;
+use serde::Deserialize;
+
+use super::{
+    Alert, Boss, BossDefinition, Chase, Chaser, Coordination, Courage, Crowded, Fan, Fear, Flee,
+    Health, Leash, Melee, Memory, Path, Perception, Ranged, Regeneration, Retreat, Skirmish, Slam,
+    Steering, Summon, Targeting, Threat, Wander,
+};
+use crate::{
+    actions::{Action, JumpState, Momentum},
+    Faction,
+};
+
+/// Every mob archetype, relative to `assets`. Listed rather than found with
+/// `load_folder`, which finds nothing on the web.
+pub const MOB_ARCHETYPES: &[&str] = &[
+    "mobs/enemy.mob.ron",
+    "mobs/ranged.mob.ron",
+    "mobs/warden.mob.ron",
+];
+
+/// Seconds without damage before a mob starts regenerating.
+const REGENERATION_DELAY: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum ColliderShape {
+    /// Standing upright, from the feet up to `height`.
+    Capsule {
+        radius: f32,
+        height: f32,
+    },
+    Ball {
+        radius: f32,
+    },
+    Cuboid {
+        half_extents: (f32, f32, f32),
+    },
+}
+
+impl ColliderShape {
+    /// The collider, with its bottom at the entity's feet.
+    pub fn collider(self) -> Collider {
+        match self {
+            Self::Capsule { radius, height } => Collider::capsule(
+                Vec3::Y * radius,
+                Vec3::Y * (height - radius).max(radius),
+                radius,
+            ),
+            Self::Ball { radius } => Collider::compound(vec![(
+                Vec3::Y * radius,
+                Quat::IDENTITY,
+                Collider::ball(radius),
+            )]),
+            Self::Cuboid {
+                half_extents: (x, y, z),
+            } => Collider::compound(vec![(
+                Vec3::Y * y,
+                Quat::IDENTITY,
+                Collider::cuboid(x, y, z),
+            )]),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum AttackDefinition {
+    Melee(Melee),
+    Ranged(Ranged),
+    Slam(Slam),
+    Fan(Fan),
+    Summon(Summon),
+}
+
+impl AttackDefinition {
+    /// Give `mob` this attack.
+    pub fn insert(&self, mob: &mut EntityCommands) {
+        match self {
+            Self::Melee(melee) => mob.insert(melee.clone()),
+            Self::Ranged(ranged) => mob.insert(ranged.clone()),
+            Self::Slam(slam) => mob.insert(slam.clone()),
+            Self::Fan(fan) => mob.insert(fan.clone()),
+            Self::Summon(summon) => mob.insert(summon.clone()),
+        };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum ScorerKind {
+    Alert,
+    Fear,
+    Crowded,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+pub enum ActionKind {
+    Chase,
+    Flee,
+    Skirmish,
+    Retreat,
+}
+
+/// One choice of the thinker: do `then` when `when` scores highest.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ThinkerChoice {
+    pub when: ScorerKind,
+    pub then: ActionKind,
+    #[serde(default = "one")]
+    pub weight: f32,
+}
+
+fn one() -> f32 {
+    1.0
+}
+
+fn default_jump() -> f32 {
+    30.0
+}
+
+fn default_step() -> f32 {
+    0.5
+}
+
+#[derive(Debug, Deserialize, TypeUuid, TypePath)]
+#[uuid = "2f8d1c5a-47b3-4e6a-9d0f-6b1e3a7c8e52"]
+pub struct MobArchetype {
+    pub name: String,
+    /// Path of the scene to show, relative to `assets`.
+    pub model: String,
+    pub collider: ColliderShape,
+    pub health: f32,
+    /// Health regained per second after a while without damage.
+    #[serde(default)]
+    pub regeneration: f32,
+    #[serde(default = "default_jump")]
+    pub jump: f32,
+    /// The highest step the mob climbs.
+    #[serde(default = "default_step")]
+    pub step: f32,
+    pub faction: Faction,
+    #[serde(default)]
+    pub attacks: Vec<AttackDefinition>,
+    pub thinker: Vec<ThinkerChoice>,
+    #[serde(default)]
+    pub perception: Perception,
+    #[serde(default)]
+    pub targeting: Targeting,
+    #[serde(default)]
+    pub courage: Courage,
+    #[serde(default)]
+    pub steering: Steering,
+    /// Whether and how the mob takes turns attacking with its allies.
+    #[serde(default)]
+    pub coordination: Option<Coordination>,
+    #[serde(default)]
+    pub boss: Option<BossDefinition>,
+}
+
+/// How much each choice of the thinker counts for a mob, by its scorer and
+/// action.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ScoreWeights(pub HashMap<(ScorerKind, ActionKind), f32>);
+
+impl ScoreWeights {
+    pub fn get(&self, scorer: ScorerKind, then: ActionKind) -> f32 {
+        self.0.get(&(scorer, then)).copied().unwrap_or(1.0)
+    }
+
+    /// Set the weight of every choice in `choices`.
+    pub fn set(&mut self, choices: &[ThinkerChoice]) {
+        self.0.extend(
+            choices
+                .iter()
+                .map(|choice| ((choice.when, choice.then), choice.weight)),
+        );
+    }
+}
+
+/// `score` from `scorer` for the choice leading to `then`, weighted for a
+/// mob that may not have `ScoreWeights`. Scores stay within 0.0 to 1.0, as
+/// big-brain requires.
+pub fn weighted(
+    weights: Option<&ScoreWeights>,
+    scorer: ScorerKind,
+    then: ActionKind,
+    score: f32,
+) -> f32 {
+    let weight = weights.map_or(1.0, |weights| weights.get(scorer, then));
+    (score * weight).clamp(0.0, 1.0)
+}
+
+fn when_then(
+    thinker: ThinkerBuilder,
+    scorer: impl ScorerBuilder + 'static,
+    action: ActionKind,
+) -> ThinkerBuilder {
+    match action {
+        ActionKind::Chase => thinker.when(scorer, Chase),
+        ActionKind::Flee => thinker.when(scorer, Flee::default()),
+        ActionKind::Skirmish => thinker.when(scorer, Skirmish::default()),
+        ActionKind::Retreat => thinker.when(scorer, Retreat),
+    }
+}
+
+impl MobArchetype {
+    pub fn thinker(&self) -> ThinkerBuilder {
+        let mut thinker = Thinker::build().picker(Highest);
+        for choice in &self.thinker {
+            let then = choice.then;
+            thinker = match choice.when {
+                ScorerKind::Alert => when_then(thinker, Alert { then }, then),
+                ScorerKind::Fear => when_then(thinker, Fear { then }, then),
+                ScorerKind::Crowded => when_then(thinker, Crowded { then }, then),
+            };
+        }
+        thinker.otherwise(Wander::default())
+    }
+
+    pub fn weights(&self) -> ScoreWeights {
+        let mut weights = ScoreWeights::default();
+        weights.set(&self.thinker);
+        weights
+    }
+}
+
+#[derive(Default)]
+pub struct MobArchetypeLoader;
+
+impl AssetLoader for MobArchetypeLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let archetype: MobArchetype = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(archetype));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["mob.ron"]
+    }
+}
+
+/// Every loaded mob archetype, by name.
+#[derive(Resource, Default)]
+pub struct MobRegistry {
+    /// Keeps the archetypes loaded.
+    handles: Vec<Handle<MobArchetype>>,
+    by_name: HashMap<String, Handle<MobArchetype>>,
+}
+
+impl MobRegistry {
+    pub fn get<'a>(
+        &self,
+        name: &str,
+        archetypes: &'a Assets<MobArchetype>,
+    ) -> Option<&'a MobArchetype> {
+        self.by_name
+            .get(name)
+            .and_then(|handle| archetypes.get(handle))
+    }
+
+    /// Whether an archetype called `name` has loaded.
+    pub fn contains(&self, name: &str, archetypes: &Assets<MobArchetype>) -> bool {
+        self.handles
+            .iter()
+            .filter_map(|handle| archetypes.get(handle))
+            .any(|archetype| archetype.name == name)
+    }
+
+    /// Whether every archetype has loaded or failed to.
+    pub fn is_ready(&self, asset_server: &AssetServer) -> bool {
+        !self.handles.is_empty()
+            && self.handles.iter().all(|handle| {
+                matches!(
+                    asset_server.get_load_state(handle),
+                    LoadState::Loaded | LoadState::Failed
+                )
+            })
+    }
+
+    /// Start loading `MOB_ARCHETYPES`.
+    pub fn load_system(
+        mut registry: ResMut<MobRegistry>,
+        asset_server: Res<AssetServer>,
+        mut loading: ResMut<AssetsLoading>,
+    ) {
+        if !registry.handles.is_empty() {
+            return;
+        }
+
+        for path in MOB_ARCHETYPES {
+            let handle: Handle<MobArchetype> = asset_server.load(*path);
+            loading.add(&handle);
+            registry.handles.push(handle);
+        }
+    }
+
+    /// Keep names up to date as archetypes load or change.
+    pub fn index_system(
+        mut registry: ResMut<MobRegistry>,
+        mut events: EventReader<AssetEvent<MobArchetype>>,
+        archetypes: Res<Assets<MobArchetype>>,
+    ) {
+        for event in &mut events {
+            match event {
+                AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                    let Some(archetype) = archetypes.get(handle) else {
+                        continue;
+                    };
+                    registry.by_name.retain(|_, other| other != handle);
+                    registry
+                        .by_name
+                        .insert(archetype.name.clone(), handle.clone_weak());
+                }
+                AssetEvent::Removed { handle } => {
+                    registry.by_name.retain(|_, other| other != handle);
+                }
+            }
+        }
+    }
+}
+
+/// Spawn a mob of the named archetype, standing at `position`.
+#[derive(Event, Clone, Debug)]
+pub struct SpawnMob {
+    pub archetype: String,
+    pub position: Vec3,
+}
+
+impl SpawnMob {
+    pub fn system(
+        mut commands: Commands,
+        asset_server: Res<AssetServer>,
+        registry: Res<MobRegistry>,
+        archetypes: Res<Assets<MobArchetype>>,
+        mut events: EventReader<SpawnMob>,
+    ) {
+        for SpawnMob {
+            archetype: name,
+            position,
+        } in &mut events
+        {
+            let Some(archetype) = registry.get(name, &archetypes) else {
+                error!("Unknown mob archetype {name:?}");
+                continue;
+            };
+
+            let mut mob = commands.spawn(SceneBundle {
+                transform: Transform::from_translation(*position),
+                scene: asset_server.load(archetype.model.as_str()),
+                ..default()
+            });
+
+            mob.insert(InputManagerBundle::<Action>::default())
+                .insert(KinematicCharacterController {
+                    autostep: Some(CharacterAutostep {
+                        max_height: CharacterLength::Absolute(archetype.step),
+                        min_width: CharacterLength::Absolute(0.2),
+                        include_dynamic_bodies: true,
+                    }),
+                    ..default()
+                })
+                .insert(RigidBody::KinematicPositionBased)
+                .insert(archetype.faction)
+                .insert(Health::new(archetype.health))
+                .insert(archetype.collider.collider())
+                .insert(JumpState::from(archetype.jump))
+                .insert(Momentum::default())
+                .insert(Chaser::default())
+                .insert(Path::default())
+                .insert(archetype.steering.clone())
+                .insert(archetype.perception.clone())
+                .insert(Memory::default())
+                .insert(archetype.targeting.clone())
+                .insert(Threat::default())
+                .insert(Leash::new(*position))
+                .insert(archetype.courage.clone())
+                .insert(archetype.weights())
+                .insert(archetype.thinker());
+
+            if archetype.regeneration > 0.0 {
+                mob.insert(Regeneration::new(
+                    archetype.regeneration,
+                    REGENERATION_DELAY,
+                ));
+            }
+
+            for attack in &archetype.attacks {
+                attack.insert(&mut mob);
+            }
+
+            if let Some(coordination) = &archetype.coordination {
+                mob.insert(coordination.clone());
+            }
+
+            if let Some(boss) = &archetype.boss {
+                mob.insert(Boss::from(boss));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The example from the module docs.
+    const EXAMPLE: &str = r#"(
+        name: "enemy",
+        model: "models/world/enemy.glb#Scene0",
+        collider: Capsule(radius: 0.25, height: 1.05),
+        health: 3.0,
+        faction: 1,
+        attacks: [Melee((damage: 1.0, reach: 1.2))],
+        thinker: [
+            (when: Fear, then: Flee),
+            (when: Alert, then: Chase, weight: 0.9),
+        ],
+    )"#;
+
+    fn parse(source: &str) -> MobArchetype {
+        ron::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn the_bundled_archetypes_parse() {
+        let enemy = parse(include_str!("../../assets/mobs/enemy.mob.ron"));
+        let ranged = parse(include_str!("../../assets/mobs/ranged.mob.ron"));
+        let warden = parse(include_str!("../../assets/mobs/warden.mob.ron"));
+
+        assert_eq!(enemy.name, "enemy");
+        assert_eq!(ranged.name, "ranged");
+        assert!(matches!(ranged.attacks[..], [AttackDefinition::Ranged(_)]));
+        assert_eq!(warden.boss.map(|boss| boss.phases.len()), Some(2));
+    }
+
+    #[test]
+    fn fields_left_out_take_their_defaults() {
+        let archetype = parse(EXAMPLE);
+
+        assert!(archetype.regeneration.abs() < f32::EPSILON);
+        assert!((archetype.jump - default_jump()).abs() < f32::EPSILON);
+        assert!((archetype.step - default_step()).abs() < f32::EPSILON);
+        assert!((archetype.thinker[0].weight - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn weights_come_from_the_thinker() {
+        let weights = parse(EXAMPLE).weights();
+
+        assert!((weights.get(ScorerKind::Alert, ActionKind::Chase) - 0.9).abs() < f32::EPSILON);
+        assert!((weights.get(ScorerKind::Fear, ActionKind::Flee) - 1.0).abs() < f32::EPSILON);
+        assert!((weights.get(ScorerKind::Alert, ActionKind::Skirmish) - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn setting_weights_only_changes_the_choices_given() {
+        let mut weights = parse(EXAMPLE).weights();
+        let phase: Vec<ThinkerChoice> =
+            ron::from_str("[(when: Alert, then: Skirmish, weight: 0.0)]").unwrap();
+
+        weights.set(&phase);
+
+        assert!(weights.get(ScorerKind::Alert, ActionKind::Skirmish).abs() < f32::EPSILON);
+        assert!((weights.get(ScorerKind::Alert, ActionKind::Chase) - 0.9).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn weighted_scores_stay_in_range() {
+        let weights = ScoreWeights(
+            [((ScorerKind::Alert, ActionKind::Chase), 2.0)]
+                .into_iter()
+                .collect(),
+        );
+
+        let alert = |weights, score| weighted(weights, ScorerKind::Alert, ActionKind::Chase, score);
+        assert!((alert(Some(&weights), 0.8) - 1.0).abs() < f32::EPSILON);
+        assert!((alert(None, 0.8) - 0.8).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn rejects_unknown_scorers() {
+        let source = EXAMPLE.replace("when: Fear", "when: Bored");
+        assert!(ron::from_str::<MobArchetype>(&source).is_err());
+    }
+}
@@ -0,0 +1,433 @@
+//! Mobs that change as they get hurt.
+//!
+//! A `Boss` goes through its `phases` as its health drops below each phase's
+//! threshold. A new phase can change how much each of its thinker's choices
+//! counts, give the boss new attacks, and make it `Unstoppable`. Every new
+//! phase is announced with `BossPhaseChanged` and the camera turns to the boss
+//! for a moment.
+//!
+//! Besides `Melee` and `Ranged`, bosses have attacks of their own: a `Slam`
+//! that hits everything around them, a `Fan` of projectiles, and `Summon`ing
+//! adds.
+
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use super::{
+    line_of_sight, AttackDefinition, Fleeing, Health, Memory, ProjectileAssets, ScoreWeights,
+    SpawnMob, ThinkerChoice, EYE_HEIGHT, PROJECTILE_HEIGHT, PROJECTILE_KNOCKBACK,
+    PROJECTILE_RADIUS,
+};
+use crate::{
+    actions::{
+        attack::{AttackBundle, Effect, PendingAttack},
+        conditions::{Condition, Locked, Unstoppable},
+    },
+    camera::CameraFocus,
+    Faction, Game,
+};
+
+/// How long the camera stays on a boss entering a new phase.
+const FOCUS_DURATION: Duration = Duration::from_millis(1500);
+/// How long a slam's hit box lasts.
+const SLAM_DURATION: Duration = Duration::from_millis(150);
+const SLAM_HEIGHT: f32 = 1.0;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BossPhase {
+    /// Announced when the phase starts.
+    pub name: String,
+    /// Start once health drops below this fraction of max.
+    pub below: f32,
+    /// Thinker choices to weigh differently, e.g.
+    /// `[(when: Crowded, then: Retreat, weight: 0.0)]`.
+    #[serde(default)]
+    pub weights: Vec<ThinkerChoice>,
+    /// Attacks to add.
+    #[serde(default)]
+    pub attacks: Vec<AttackDefinition>,
+    /// Whether to become immune to `Locked`.
+    #[serde(default)]
+    pub unstoppable: bool,
+}
+
+/// What makes a kind of mob a boss.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BossDefinition {
+    /// Shown over its health bar.
+    pub title: String,
+    /// In the order they start.
+    pub phases: Vec<BossPhase>,
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Boss {
+    pub title: String,
+    pub phases: Vec<BossPhase>,
+    /// How many phases have started.
+    pub phase: usize,
+}
+
+impl From<&BossDefinition> for Boss {
+    fn from(definition: &BossDefinition) -> Self {
+        Self {
+            title: definition.title.clone(),
+            phases: definition.phases.clone(),
+            phase: 0,
+        }
+    }
+}
+
+/// Sent when a boss starts a new phase.
+#[derive(Event, Clone, Debug)]
+pub struct BossPhaseChanged {
+    pub boss: Entity,
+    /// How many phases have started.
+    pub phase: usize,
+    pub name: String,
+}
+
+impl Boss {
+    /// The next phase, if health has dropped below its threshold, as a
+    /// fraction of max.
+    fn next_phase(&mut self, ratio: f32) -> Option<BossPhase> {
+        let phase = self
+            .phases
+            .get(self.phase)
+            .filter(|phase| ratio < phase.below)?
+            .clone();
+        self.phase += 1;
+        Some(phase)
+    }
+
+    /// Start every phase whose threshold health has dropped below.
+    pub fn phase_system(
+        mut commands: Commands,
+        mut bosses: Query<(Entity, &mut Boss, &Health, &mut ScoreWeights), Changed<Health>>,
+        mut focus: ResMut<CameraFocus>,
+        mut changes: EventWriter<BossPhaseChanged>,
+    ) {
+        for (entity, mut boss, health, mut weights) in &mut bosses {
+            if health.current <= 0.0 {
+                continue;
+            }
+
+            while let Some(phase) = boss.next_phase(health.ratio()) {
+                info!("{}: {}", boss.title, phase.name);
+
+                weights.set(&phase.weights);
+
+                let mut mob = commands.entity(entity);
+                for attack in &phase.attacks {
+                    attack.insert(&mut mob);
+                }
+                if phase.unstoppable {
+                    mob.insert(Unstoppable).remove::<Condition<Locked>>();
+                }
+
+                focus.focus(entity, FOCUS_DURATION);
+                changes.send(BossPhaseChanged {
+                    boss: entity,
+                    phase: boss.phase,
+                    name: phase.name,
+                });
+            }
+        }
+    }
+}
+
+/// Hits everything around the mob, knocking it away.
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Slam {
+    pub damage: f32,
+    /// How far around the mob the slam reaches, and how close the target
+    /// has to be to slam.
+    pub radius: f32,
+    /// Seconds between starting a slam and it landing.
+    pub wind_up: f32,
+    /// Seconds between slams.
+    pub cooldown: f32,
+    /// Knockback away from the mob.
+    pub push: f32,
+    #[serde(skip)]
+    pub reload: Timer,
+}
+
+impl Default for Slam {
+    fn default() -> Self {
+        Self {
+            damage: 2.0,
+            radius: 2.5,
+            wind_up: 0.8,
+            cooldown: 6.0,
+            push: 6.0,
+            reload: Timer::default(),
+        }
+    }
+}
+
+impl Slam {
+    pub fn system(
+        mut commands: Commands,
+        mut mobs: Query<
+            (Entity, &Transform, &Faction, &Memory, &mut Slam),
+            (
+                Without<Condition<Locked>>,
+                Without<PendingAttack>,
+                Without<Fleeing>,
+            ),
+        >,
+        time: Res<Time>,
+    ) {
+        for (entity, transform, faction, memory, mut slam) in &mut mobs {
+            slam.reload.tick(time.delta());
+            if !slam.reload.finished() || !memory.sensed {
+                continue;
+            }
+            let Some(target) = memory.last_seen else {
+                continue;
+            };
+            if target.xz().distance(transform.translation.xz()) > slam.radius {
+                continue;
+            }
+
+            slam.reload = Timer::from_seconds(slam.cooldown, TimerMode::Once);
+
+            let attack = AttackBundle::new(
+                Effect::new(slam.damage, vec![], Vec3::ZERO).with_push(slam.push),
+                Collider::cylinder(SLAM_HEIGHT / 2.0, slam.radius),
+                *faction,
+                SLAM_DURATION,
+                Transform::from_translation(transform.translation + Vec3::Y * SLAM_HEIGHT / 2.0),
+            )
+            .with_source(entity);
+
+            commands.entity(entity).insert(PendingAttack::new(
+                attack,
+                Duration::from_secs_f32(slam.wind_up),
+            ));
+        }
+    }
+}
+
+/// Fires several projectiles at once, spread out around the target.
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Fan {
+    pub count: usize,
+    /// Degrees between the outermost projectiles.
+    pub spread: f32,
+    /// How far projectiles fly.
+    pub range: f32,
+    pub projectile_speed: f32,
+    pub damage: f32,
+    /// Seconds between fans.
+    pub cooldown: f32,
+    #[serde(skip)]
+    pub reload: Timer,
+}
+
+impl Default for Fan {
+    fn default() -> Self {
+        Self {
+            count: 5,
+            spread: 60.0,
+            range: 8.0,
+            projectile_speed: 6.0,
+            damage: 1.0,
+            cooldown: 4.0,
+            reload: Timer::default(),
+        }
+    }
+}
+
+impl Fan {
+    pub fn system(
+        mut commands: Commands,
+        rapier: Res<RapierContext>,
+        assets: Res<ProjectileAssets>,
+        mut mobs: Query<
+            (Entity, &mut Transform, &Faction, &Memory, &mut Fan),
+            (Without<Condition<Locked>>, Without<Fleeing>),
+        >,
+        time: Res<Time>,
+    ) {
+        for (entity, mut transform, faction, memory, mut fan) in &mut mobs {
+            fan.reload.tick(time.delta());
+            if !fan.reload.finished() || !memory.sensed || fan.count == 0 {
+                continue;
+            }
+            let Some(target) = memory.last_seen else {
+                continue;
+            };
+            if target.xz().distance(transform.translation.xz()) > fan.range {
+                continue;
+            }
+
+            let eye = transform.translation + Vec3::Y * EYE_HEIGHT;
+            if !line_of_sight(&rapier, eye, target + Vec3::Y * EYE_HEIGHT) {
+                continue;
+            }
+
+            let aim = (target - transform.translation).xz().normalize_or_zero();
+            if aim == Vec2::ZERO {
+                continue;
+            }
+
+            transform.rotation = crate::utils::look_to(Vec3::new(aim.x, 0.0, aim.y));
+            fan.reload = Timer::from_seconds(fan.cooldown, TimerMode::Once);
+
+            let from = transform.translation + Vec3::Y * PROJECTILE_HEIGHT;
+            let flight = Duration::from_secs_f32(fan.range / fan.projectile_speed);
+            let step = if fan.count > 1 {
+                fan.spread / (fan.count - 1) as f32
+            } else {
+                0.0
+            };
+
+            for n in 0..fan.count {
+                let angle = (n as f32 * step - fan.spread / 2.0).to_radians();
+                let turned = Vec2::from_angle(angle).rotate(aim);
+                let direction = Vec3::new(turned.x, 0.0, turned.y);
+
+                let projectile = AttackBundle::new(
+                    Effect::new(fan.damage, vec![], direction * PROJECTILE_KNOCKBACK),
+                    Collider::ball(PROJECTILE_RADIUS),
+                    *faction,
+                    flight,
+                    Transform::from_translation(from),
+                )
+                .with_source(entity)
+                .with_velocity(direction * fan.projectile_speed);
+
+                commands.spawn(projectile).insert((
+                    assets.mesh.clone(),
+                    assets.material.clone(),
+                    Visibility::default(),
+                    ComputedVisibility::default(),
+                ));
+            }
+        }
+    }
+}
+
+/// Calls in mobs of another archetype around the mob.
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Summon {
+    pub archetype: String,
+    pub count: usize,
+    /// How far from the mob the adds appear.
+    pub radius: f32,
+    /// Seconds between summons.
+    pub cooldown: f32,
+    #[serde(skip)]
+    pub reload: Timer,
+}
+
+impl Default for Summon {
+    fn default() -> Self {
+        Self {
+            archetype: "enemy".to_string(),
+            count: 2,
+            radius: 1.5,
+            cooldown: 12.0,
+            reload: Timer::default(),
+        }
+    }
+}
+
+impl Summon {
+    pub fn system(
+        game: Res<Game>,
+        mut mobs: Query<
+            (&Transform, &Memory, &mut Summon),
+            (Without<Condition<Locked>>, Without<Fleeing>),
+        >,
+        mut spawns: EventWriter<SpawnMob>,
+        time: Res<Time>,
+    ) {
+        for (transform, memory, mut summon) in &mut mobs {
+            summon.reload.tick(time.delta());
+            if !summon.reload.finished() || !memory.sensed {
+                continue;
+            }
+
+            summon.reload = Timer::from_seconds(summon.cooldown, TimerMode::Once);
+
+            for n in 0..summon.count {
+                let angle = TAU * n as f32 / summon.count as f32;
+                let around = Vec3::new(angle.cos(), 0.0, angle.sin()) * summon.radius;
+                let Some(position) = game
+                    .world_to_cell(transform.translation + around)
+                    .filter(|cell| game.cell(*cell).is_some_and(|cell| cell.kind.is_walkable()))
+                    .and_then(|cell| game.cell_to_world(cell))
+                else {
+                    continue;
+                };
+
+                spawns.send(SpawnMob {
+                    archetype: summon.archetype.clone(),
+                    position: position + Vec3::Y,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boss(thresholds: &[f32]) -> Boss {
+        Boss {
+            title: "Boss".to_string(),
+            phases: thresholds
+                .iter()
+                .map(|below| BossPhase {
+                    name: format!("below {below}"),
+                    below: *below,
+                    weights: Vec::new(),
+                    attacks: Vec::new(),
+                    unstoppable: false,
+                })
+                .collect(),
+            phase: 0,
+        }
+    }
+
+    #[test]
+    fn waits_for_health_to_drop_below_the_threshold() {
+        let mut boss = boss(&[0.66, 0.33]);
+
+        assert!(boss.next_phase(1.0).is_none());
+        assert!(boss.next_phase(0.66).is_none());
+        assert_eq!(boss.phase, 0);
+    }
+
+    #[test]
+    fn starts_each_phase_once() {
+        let mut boss = boss(&[0.66, 0.33]);
+
+        assert_eq!(boss.next_phase(0.5).unwrap().name, "below 0.66");
+        assert!(boss.next_phase(0.5).is_none());
+        assert!(boss.next_phase(0.4).is_none());
+        assert_eq!(boss.phase, 1);
+    }
+
+    #[test]
+    fn a_big_hit_starts_every_phase_passed_in_order() {
+        let mut boss = boss(&[0.66, 0.33]);
+
+        assert_eq!(boss.next_phase(0.1).unwrap().name, "below 0.66");
+        assert_eq!(boss.next_phase(0.1).unwrap().name, "below 0.33");
+        assert!(boss.next_phase(0.1).is_none());
+        assert_eq!(boss.phase, 2);
+    }
+}
@@ -0,0 +1,302 @@
+//! Running away when hurt.
+//!
+//! `Fear` scores high once a mob's health, plus a little for every ally close
+//! by, drops below what its `Courage` can take. The mob then `Flee`s to the
+//! safe cell furthest from where it last knew its target to be, leaning
+//! towards its allies if it regroups. It keeps fleeing until `Regeneration`
+//! has brought it back to `recover_at`, and then goes back to fighting.
+
+use bevy::ecs::query::Has;
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use big_brain::actions::ActionState as BBActionState;
+use big_brain::prelude::*;
+use leafwing_input_manager::action_state::ActionState;
+use serde::Deserialize;
+
+use super::{
+    move_towards, weighted, ActionKind, Chaser, Health, Memory, Path, ScoreWeights, ScorerKind,
+};
+use crate::{actions::Action, board::GridPosition, Faction, FactionRelations, Game, Relation};
+
+/// How many cells away to look for somewhere to flee to.
+const REFUGE_RANGE: usize = 6;
+/// Allies further than this aren't regrouped with.
+const REGROUP_RANGE: f32 = 12.0;
+/// How much being close to allies counts against being far from the threat.
+const REGROUP_WEIGHT: f32 = 0.5;
+/// Seconds between picking a new refuge.
+const REPLAN_INTERVAL: f32 = 1.0;
+
+#[derive(Debug, Clone, Component, ScorerBuilder)]
+pub struct Fear {
+    /// The action its choice leads to, which the choice's weight is for.
+    pub then: ActionKind,
+}
+
+#[derive(Default, Debug, Clone, Component, ActionBuilder)]
+pub struct Flee {
+    pub refuge: Option<(usize, usize)>,
+    pub timer: f32,
+}
+
+/// How much a kind of mob takes before running away.
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Courage {
+    /// Flee below this fraction of max health.
+    pub flee_below: f32,
+    /// Stop fleeing once back at this fraction of max health.
+    pub recover_at: f32,
+    /// Each ally within `ally_radius` counts as this much more health.
+    pub ally_bonus: f32,
+    pub ally_radius: f32,
+    /// Whether to flee towards allies rather than just away.
+    pub regroup: bool,
+}
+
+impl Default for Courage {
+    fn default() -> Self {
+        Self {
+            flee_below: 0.35,
+            recover_at: 0.8,
+            ally_bonus: 0.1,
+            ally_radius: 4.0,
+            regroup: true,
+        }
+    }
+}
+
+/// On mobs that are fleeing, until they have recovered.
+#[derive(Component, Clone, Copy, Debug)]
+#[component(storage = "SparseSet")]
+pub struct Fleeing;
+
+/// The safe cell within `REFUGE_RANGE` of `from` furthest from `threat`,
+/// and closest to `allies` if given. `from` itself with neither.
+fn find_refuge(
+    game: &Game,
+    from: (usize, usize),
+    threat: Option<Vec3>,
+    allies: Option<Vec3>,
+) -> Option<(usize, usize)> {
+    if threat.is_none() && allies.is_none() {
+        return Some(from);
+    }
+
+    let (width, height) = game.board_size();
+    let (i, j) = from;
+    let columns = i.saturating_sub(REFUGE_RANGE)..=(i + REFUGE_RANGE).min(width.saturating_sub(1));
+    let rows = j.saturating_sub(REFUGE_RANGE)..=(j + REFUGE_RANGE).min(height.saturating_sub(1));
+
+    columns
+        .flat_map(|i| rows.clone().map(move |j| (i, j)))
+        .filter(|cell| game.cell(*cell).is_some_and(|cell| cell.kind.is_safe()))
+        .filter_map(|cell| {
+            game.cell_to_world(cell)
+                .map(|position| (cell, position.xz()))
+        })
+        .map(|(cell, position)| {
+            let away = threat.map_or(0.0, |threat| threat.xz().distance(position));
+            let apart = allies.map_or(0.0, |allies| allies.xz().distance(position));
+            (cell, away - apart * REGROUP_WEIGHT)
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(cell, _)| cell)
+}
+
+/// Afraid while hurt and short of allies, and until healed again.
+pub fn fear_scorer_system(
+    mut commands: Commands,
+    relations: Res<FactionRelations>,
+    mobs: Query<(
+        Entity,
+        &Transform,
+        &Faction,
+        &Health,
+        &Courage,
+        Has<Fleeing>,
+        Option<&ScoreWeights>,
+    )>,
+    allies: Query<(Entity, &Transform, &Faction), With<Courage>>,
+    mut query: Query<(&Actor, &mut Score, &Fear)>,
+) {
+    for (Actor(actor), mut score, fear) in &mut query {
+        let Ok((entity, transform, faction, health, courage, fleeing, weights)) = mobs.get(*actor)
+        else {
+            score.set(0.0);
+            continue;
+        };
+
+        if fleeing {
+            if health.ratio() >= courage.recover_at {
+                commands.entity(entity).remove::<Fleeing>();
+                score.set(0.0);
+            } else {
+                score.set(weighted(weights, ScorerKind::Fear, fear.then, 1.0));
+            }
+            continue;
+        }
+
+        let nearby = allies
+            .iter()
+            .filter(|(ally, ally_transform, ally_faction)| {
+                *ally != entity
+                    && relations.get(*faction, **ally_faction) == Relation::Friendly
+                    && ally_transform.translation.distance(transform.translation)
+                        <= courage.ally_radius
+            })
+            .count();
+
+        if health.ratio() + nearby as f32 * courage.ally_bonus < courage.flee_below {
+            commands.entity(entity).insert(Fleeing);
+            score.set(weighted(weights, ScorerKind::Fear, fear.then, 1.0));
+        } else {
+            score.set(0.0);
+        }
+    }
+}
+
+/// Head for a refuge away from the target, picking a new one every
+/// `REPLAN_INTERVAL` as things move.
+pub fn flee_action_system(
+    game: Res<Game>,
+    relations: Res<FactionRelations>,
+    mut mobs: Query<(
+        &mut ActionState<Action>,
+        &Transform,
+        &Faction,
+        &GridPosition,
+        &Courage,
+        &Memory,
+        &Path,
+        &mut Chaser,
+    )>,
+    allies: Query<(Entity, &Transform, &Faction), With<Courage>>,
+    mut query: Query<(&Actor, &mut BBActionState, &mut Flee)>,
+    time: Res<Time>,
+) {
+    for (Actor(actor), mut state, mut flee) in &mut query {
+        let Ok((mut action_state, transform, faction, position, courage, memory, path, mut chaser)) =
+            mobs.get_mut(*actor)
+        else {
+            continue;
+        };
+
+        match *state {
+            BBActionState::Requested => {
+                flee.refuge = None;
+                *state = BBActionState::Executing;
+            }
+            BBActionState::Cancelled => {
+                action_state.release(Action::Move);
+                *state = BBActionState::Failure;
+                continue;
+            }
+            BBActionState::Executing => {}
+            _ => continue,
+        }
+
+        flee.timer -= time.delta_seconds();
+        if flee.refuge.is_none() || flee.timer <= 0.0 {
+            let allies = if courage.regroup {
+                let (sum, count) = allies
+                    .iter()
+                    .filter(|(ally, ally_transform, ally_faction)| {
+                        ally != actor
+                            && relations.get(*faction, **ally_faction) == Relation::Friendly
+                            && ally_transform.translation.distance(transform.translation)
+                                <= REGROUP_RANGE
+                    })
+                    .fold((Vec3::ZERO, 0), |(sum, count), (_, ally_transform, _)| {
+                        (sum + ally_transform.translation, count + 1)
+                    });
+                (count > 0).then_some(sum / count as f32)
+            } else {
+                None
+            };
+
+            flee.refuge = find_refuge(&game, position.0, memory.last_seen, allies);
+            flee.timer = REPLAN_INTERVAL;
+        }
+
+        let Some(refuge) = flee.refuge.and_then(|cell| game.cell_to_world(cell)) else {
+            action_state.release(Action::Move);
+            continue;
+        };
+        chaser.position = Some(refuge);
+
+        // Wait for a path to the refuge before following it.
+        let next = path
+            .next(&game, refuge)
+            .filter(|_| path.goal == flee.refuge);
+        let Some(next) = next else {
+            action_state.release(Action::Move);
+            continue;
+        };
+
+        move_towards(
+            &mut action_state,
+            (next - transform.translation).xz().normalize_or_zero(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Cell, CellKind};
+
+    /// A flat board from rows of level symbols, the first row at `j = 0`.
+    fn game(rows: &[&str]) -> Game {
+        Game {
+            board: rows
+                .iter()
+                .map(|row| {
+                    row.chars()
+                        .map(|symbol| Cell {
+                            height: 0.0,
+                            kind: CellKind::from_symbol(symbol).unwrap(),
+                        })
+                        .collect()
+                })
+                .collect(),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn stays_put_with_nothing_to_run_from() {
+        let game = game(&["....."]);
+        assert_eq!(find_refuge(&game, (2, 0), None, None), Some((2, 0)));
+    }
+
+    #[test]
+    fn runs_as_far_from_the_threat_as_it_can() {
+        let game = game(&["........."]);
+        let refuge = find_refuge(&game, (4, 0), Some(Vec3::ZERO), None);
+        assert_eq!(refuge, Some((8, 0)));
+    }
+
+    #[test]
+    fn only_picks_safe_cells() {
+        let game = game(&["....^o"]);
+        let refuge = find_refuge(&game, (2, 0), Some(Vec3::ZERO), None);
+        assert_eq!(refuge, Some((3, 0)));
+    }
+
+    #[test]
+    fn leans_towards_allies() {
+        let game = game(&[
+            ".........", //
+            ".........", //
+            ".........", //
+            ".........", //
+            ".........",
+        ]);
+        let alone = find_refuge(&game, (4, 2), Some(Vec3::ZERO), None);
+        let regrouping = find_refuge(&game, (4, 2), Some(Vec3::ZERO), Some(Vec3::X * 8.0));
+        assert_eq!(alone, Some((8, 4)));
+        assert_eq!(regrouping, Some((8, 0)));
+    }
+}
@@ -1,4 +1,15 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
+use bevy_health_bar3d::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use crate::{actions::attack::Hit, ControlledPlayer, GameState, PauseState};
+
+/// Seconds without damage before a health bar starts fading out.
+const FADE_AFTER: f32 = 3.0;
+/// Seconds the fade-out takes.
+const FADE_DURATION: f32 = 0.5;
 
 #[derive(Component)]
 pub struct Health {
@@ -6,9 +17,242 @@ pub struct Health {
     pub current: f32,
 }
 
-#[derive(Bundle)]
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { max, current: max }
+    }
+
+    /// Current health as a fraction of max, from 0.0 to 1.0.
+    pub fn ratio(&self) -> f32 {
+        if self.max <= 0.0 {
+            return 0.0;
+        }
+        (self.current / self.max).clamp(0.0, 1.0)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.current >= self.max
+    }
+
+    /// Mobs that run out of health die. The player running out ends the game.
+    pub fn death_system(
+        mut commands: Commands,
+        query: Query<(Entity, &Health, Option<&ControlledPlayer>), Changed<Health>>,
+        mut next_state: ResMut<NextState<GameState>>,
+    ) {
+        for (entity, health, player) in &query {
+            if health.current > 0.0 {
+                continue;
+            }
+
+            if player.is_some() {
+                next_state.set(GameState::GameOver);
+            } else {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+/// Heals `rate` health per second once `delay` has passed without taking
+/// damage.
+#[derive(Component)]
+pub struct Regeneration {
+    pub rate: f32,
+    pub delay: Timer,
+}
+
+impl Regeneration {
+    pub fn new(rate: f32, delay: Duration) -> Self {
+        Self {
+            rate,
+            delay: Timer::new(delay, TimerMode::Once),
+        }
+    }
+
+    pub fn system(
+        mut query: Query<(&mut Regeneration, &mut Health)>,
+        mut hits: EventReader<Hit>,
+        time: Res<Time>,
+    ) {
+        for hit in &mut hits {
+            if hit.damage <= 0.0 {
+                continue;
+            }
+            if let Ok((mut regeneration, _)) = query.get_mut(hit.target) {
+                regeneration.delay.reset();
+            }
+        }
+
+        for (mut regeneration, mut health) in &mut query {
+            if !regeneration.delay.tick(time.delta()).finished() {
+                continue;
+            }
+            if health.current <= 0.0 || health.is_full() {
+                continue;
+            }
+            health.current =
+                (health.current + regeneration.rate * time.delta_seconds()).min(health.max);
+        }
+    }
+}
+
+/// What a floating health bar shows, kept in sync with its owner's `Health`.
+///
+/// This is its own component so that `HealthBarPlugin` only draws bars for
+/// `HealthBar` entities, and not for everything that has `Health`.
+#[derive(Component, Reflect, Default)]
+pub struct HealthBarValue(pub f32);
+
+impl Percentage for HealthBarValue {
+    fn value(&self) -> f32 {
+        self.0
+    }
+}
+
+/// Counts down from the last time the owner took damage.
+#[derive(Component)]
+pub struct HealthBarFade {
+    pub timer: Timer,
+}
+
+impl Default for HealthBarFade {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(FADE_AFTER + FADE_DURATION, TimerMode::Once),
+        }
+    }
+}
+
+/// A health bar floating above its parent.
+#[derive(Bundle, Default)]
 pub struct HealthBar {
-    pub health: Health,
+    pub health: HealthBarValue,
+    pub fade: HealthBarFade,
+    pub bar: BarBundle<HealthBarValue>,
     pub transform: Transform,
     pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+}
+
+#[derive(Resource, Default)]
+pub struct HealthBarSettings {
+    /// Off by default, since the HUD already shows the player's health.
+    pub show_on_player: bool,
+}
+
+impl HealthBar {
+    /// Give everything that gains `Health` a bar above its collider.
+    pub fn attach_system(
+        mut commands: Commands,
+        owners: Query<
+            (
+                Entity,
+                &Health,
+                Option<&Collider>,
+                Option<&ControlledPlayer>,
+            ),
+            Added<Health>,
+        >,
+        settings: Res<HealthBarSettings>,
+    ) {
+        for (entity, health, collider, player) in &owners {
+            if player.is_some() && !settings.show_on_player {
+                continue;
+            }
+
+            let top = collider.map_or(1.5, |collider| collider.raw.compute_local_aabb().maxs.y);
+
+            commands.entity(entity).with_children(|parent| {
+                parent.spawn(HealthBar {
+                    health: HealthBarValue(health.ratio()),
+                    transform: Transform::from_xyz(0.0, top + 0.3, 0.0),
+                    visibility: Visibility::Hidden,
+                    ..default()
+                });
+            });
+        }
+    }
+
+    pub fn sync_system(
+        owners: Query<(&Health, &Children), Changed<Health>>,
+        mut bars: Query<(
+            &mut HealthBarValue,
+            &mut HealthBarFade,
+            &mut Transform,
+            &mut Visibility,
+        )>,
+    ) {
+        for (health, children) in &owners {
+            for child in children {
+                let Ok((mut value, mut fade, mut transform, mut visibility)) = bars.get_mut(*child)
+                else {
+                    continue;
+                };
+
+                value.0 = health.ratio();
+                fade.timer.reset();
+                transform.scale = Vec3::ONE;
+                *visibility = if health.is_full() {
+                    Visibility::Hidden
+                } else {
+                    Visibility::Inherited
+                };
+            }
+        }
+    }
+
+    /// Shrink bars away once their owner hasn't been hurt in a while.
+    pub fn fade_system(
+        mut bars: Query<(&mut HealthBarFade, &mut Transform, &mut Visibility)>,
+        time: Res<Time>,
+    ) {
+        for (mut fade, mut transform, mut visibility) in &mut bars {
+            if *visibility == Visibility::Hidden {
+                continue;
+            }
+
+            fade.timer.tick(time.delta());
+            if fade.timer.finished() {
+                *visibility = Visibility::Hidden;
+                continue;
+            }
+
+            let remaining = fade.timer.remaining_secs();
+            if remaining < FADE_DURATION {
+                transform.scale = Vec3::splat(remaining / FADE_DURATION);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(HealthBarPlugin::<HealthBarValue>::default())
+            .register_type::<HealthBarValue>()
+            .insert_resource(ColorScheme::<HealthBarValue>::new().foreground_color(
+                ForegroundColor::TriSpectrum {
+                    high: Color::GREEN,
+                    moderate: Color::YELLOW,
+                    low: Color::RED,
+                },
+            ))
+            .init_resource::<HealthBarSettings>()
+            .add_systems(
+                Update,
+                (
+                    HealthBar::attach_system,
+                    HealthBar::sync_system,
+                    HealthBar::fade_system.after(HealthBar::sync_system),
+                    Health::death_system,
+                    Regeneration::system.before(Health::death_system),
+                )
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PauseState::Running)),
+            );
+    }
 }
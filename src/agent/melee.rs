@@ -0,0 +1,124 @@
+//! Mobs hitting what they have caught up with.
+//!
+//! A mob with `Melee` swings at its target whenever the target is sensed and
+//! within `reach`. The swing lands `wind_up` seconds later, in front of where
+//! the mob faced when it started, so it can be dodged. Mobs with
+//! `Coordination` only swing while they hold an `AttackSlot`.
+
+use std::time::Duration;
+
+use bevy::ecs::query::Has;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use super::{AttackSlot, Coordination, Fleeing, Memory};
+use crate::{
+    actions::{
+        attack::{AttackBundle, Effect, PendingAttack},
+        conditions::{Condition, Locked},
+    },
+    Faction,
+};
+
+/// How long a swing's hit box lasts.
+const SWING_DURATION: Duration = Duration::from_millis(100);
+/// Height above the feet the hit box is centred at.
+const SWING_HEIGHT: f32 = 0.75;
+
+/// A kind of mob's close-up attack.
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Melee {
+    pub damage: f32,
+    /// How close the target has to be to swing at it.
+    pub reach: f32,
+    /// Seconds between swings.
+    pub cooldown: f32,
+    /// Seconds between starting a swing and it landing.
+    pub wind_up: f32,
+    pub knockback: f32,
+    #[serde(skip)]
+    pub reload: Timer,
+}
+
+impl Default for Melee {
+    fn default() -> Self {
+        Self {
+            damage: 1.0,
+            reach: 1.2,
+            cooldown: 1.5,
+            wind_up: 0.3,
+            knockback: 3.0,
+            reload: Timer::default(),
+        }
+    }
+}
+
+impl Melee {
+    pub fn system(
+        mut commands: Commands,
+        mut mobs: Query<
+            (
+                Entity,
+                &mut Transform,
+                &Faction,
+                &Memory,
+                &mut Melee,
+                Has<Coordination>,
+                Has<AttackSlot>,
+            ),
+            (
+                Without<Condition<Locked>>,
+                Without<PendingAttack>,
+                Without<Fleeing>,
+            ),
+        >,
+        time: Res<Time>,
+    ) {
+        for (entity, mut transform, faction, memory, mut melee, coordinated, slot) in &mut mobs {
+            melee.reload.tick(time.delta());
+            if !melee.reload.finished() || !memory.sensed || (coordinated && !slot) {
+                continue;
+            }
+            let Some(target) = memory.last_seen else {
+                continue;
+            };
+
+            let offset = Vec3::new(
+                target.x - transform.translation.x,
+                0.0,
+                target.z - transform.translation.z,
+            );
+            if offset.length() > melee.reach {
+                continue;
+            }
+
+            let direction = offset.normalize_or_zero();
+            if direction != Vec3::ZERO {
+                transform.rotation = crate::utils::look_to(direction);
+            }
+            melee.reload = Timer::from_seconds(melee.cooldown, TimerMode::Once);
+
+            let hit_box = melee.reach / 2.0;
+            let attack_transform = Transform::from_translation(
+                transform.translation + direction * hit_box + Vec3::Y * SWING_HEIGHT,
+            )
+            .with_rotation(transform.rotation);
+
+            let swing = AttackBundle::new(
+                Effect::new(melee.damage, vec![], direction * melee.knockback),
+                Collider::cuboid(hit_box, 0.5, hit_box),
+                *faction,
+                SWING_DURATION,
+                attack_transform,
+            )
+            .with_source(entity);
+
+            commands.entity(entity).insert(PendingAttack::new(
+                swing,
+                Duration::from_secs_f32(melee.wind_up),
+            ));
+        }
+    }
+}
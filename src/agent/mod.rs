@@ -7,21 +7,51 @@
 //
 // We will implement the agent as a utility AI.
 
-use crate::{actions::JumpState, Faction, GameState};
+use crate::{Faction, FactionRelations, Game, GameState, PauseState, Relation};
+
+use bevy::ecs::query::Has;
 use bevy::math::Vec3Swizzles;
 use bevy::prelude::*;
-use bevy_rapier3d::prelude::*;
 use big_brain::actions::ActionState as BBActionState;
 use big_brain::prelude::*;
 use leafwing_input_manager::{
     action_state::{ActionData, ActionState},
     axislike::DualAxisData,
-    InputManagerBundle,
 };
 
+pub mod archetype;
+pub use archetype::*;
+
+pub mod boss;
+pub use boss::*;
+
+pub mod flee;
+pub use flee::*;
+
 pub mod health;
 pub use health::*;
 
+pub mod melee;
+pub use melee::*;
+
+pub mod pathfinding;
+pub use pathfinding::*;
+
+pub mod perception;
+pub use perception::*;
+
+pub mod ranged;
+pub use ranged::*;
+
+pub mod squad;
+pub use squad::*;
+
+pub mod steering;
+pub use steering::*;
+
+pub mod targeting;
+pub use targeting::*;
+
 #[derive(Debug, Clone, Component, ScorerBuilder)]
 pub struct Wanderlust;
 
@@ -32,7 +62,10 @@ pub struct Wander {
 }
 
 #[derive(Debug, Clone, Component, ScorerBuilder)]
-pub struct Alert;
+pub struct Alert {
+    /// The action its choice leads to, which the choice's weight is for.
+    pub then: ActionKind,
+}
 
 #[derive(Default, Debug, Clone, Component, ActionBuilder)]
 pub struct Chase;
@@ -40,6 +73,8 @@ pub struct Chase;
 #[derive(Default, Debug, Clone, Component)]
 pub struct Chaser {
     pub target: Option<Entity>,
+    /// Where the target is, as far as the chaser knows.
+    pub position: Option<Vec3>,
 }
 
 #[derive(Default)]
@@ -48,49 +83,93 @@ pub struct MobPlugin;
 impl Plugin for MobPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(BigBrainPlugin::new(PreUpdate))
+            .add_asset::<MobArchetype>()
+            .init_asset_loader::<MobArchetypeLoader>()
+            .init_resource::<MobRegistry>()
+            .init_resource::<ProjectileAssets>()
+            .add_event::<SpawnMob>()
+            .add_event::<BossPhaseChanged>()
+            .add_systems(OnEnter(GameState::LoadingGame), MobRegistry::load_system)
+            .add_systems(Update, MobRegistry::index_system)
+            .add_systems(
+                Update,
+                SpawnMob::system.run_if(in_state(GameState::Playing)),
+            )
+            .configure_set(
+                PreUpdate,
+                BigBrainSet::Scorers.run_if(in_state(PauseState::Running)),
+            )
+            .configure_set(
+                PreUpdate,
+                BigBrainSet::Thinkers.run_if(in_state(PauseState::Running)),
+            )
+            .configure_set(
+                PreUpdate,
+                BigBrainSet::Actions.run_if(in_state(PauseState::Running)),
+            )
+            .add_systems(
+                Update,
+                Boss::phase_system
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PauseState::Running)),
+            )
             .add_systems(
                 PreUpdate,
-                (wanderlust_scorer_system, alert_scorer_system)
+                (Perception::system, Threat::system, callout_system)
+                    .chain()
+                    .before(BigBrainSet::Scorers)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PauseState::Running)),
+            )
+            .add_systems(
+                PreUpdate,
+                (
+                    wanderlust_scorer_system,
+                    alert_scorer_system,
+                    fear_scorer_system,
+                    crowded_scorer_system,
+                )
                     .in_set(BigBrainSet::Scorers)
                     .run_if(in_state(GameState::Playing)),
             )
             .add_systems(
                 Update,
-                (wandering_action_system, chase_action_system)
+                (
+                    wandering_action_system,
+                    flee_action_system.before(Path::plan_system),
+                    Coordination::system.before(Path::plan_system),
+                    Path::plan_system.before(chase_action_system),
+                    chase_action_system,
+                    skirmish_action_system.after(Path::plan_system),
+                    retreat_action_system,
+                    Ranged::fire_system,
+                    Melee::system,
+                    Slam::system,
+                    Fan::system,
+                    Summon::system,
+                    Steering::system
+                        .after(chase_action_system)
+                        .after(skirmish_action_system)
+                        .after(retreat_action_system),
+                )
                     .in_set(BigBrainSet::Actions)
-                    .run_if(in_state(GameState::Playing)),
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PauseState::Running)),
             );
     }
 }
 
-pub fn spawn_mob(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let thinker = Thinker::build()
-        .picker(Highest)
-        .when(Alert, Chase)
-        .otherwise(Wander::default());
-
-    commands
-        .spawn_empty()
-        .insert(SceneBundle {
-            transform: Transform {
-                translation: Vec3::new(5., 1., 7.),
-                ..default()
-            },
-            scene: asset_server.load("models/world/enemy.glb#Scene0"),
-            ..default()
-        })
-        .insert(InputManagerBundle::<crate::actions::Action>::default())
-        .insert(KinematicCharacterController::default())
-        .insert(RigidBody::KinematicPositionBased)
-        .insert(Faction(1))
-        .insert(Collider::capsule(
-            Vec3::new(0.0, 0.25, 0.0),
-            Vec3::new(0.0, 0.8, 0.0),
-            0.25,
-        ))
-        .insert(JumpState::from(30.0))
-        .insert(Chaser::default())
-        .insert(thinker);
+/// Spawn the level's mobs, each of the archetype its spawn names.
+pub fn spawn_mob(game: Res<Game>, mut spawns: EventWriter<SpawnMob>) {
+    for spawn in &game.mob_spawns {
+        let (i, j) = spawn.cell;
+        let height = game.board[j][i].height;
+
+        spawns.send(SpawnMob {
+            archetype: spawn.kind.clone(),
+            position: Vec3::new(i as f32, height + 1., j as f32),
+        });
+    }
 }
 
 pub fn wanderlust_scorer_system(mut query: Query<&mut Score, With<Wanderlust>>) {
@@ -115,104 +194,123 @@ pub fn wandering_action_system(mut query: Query<(&Actor, &mut BBActionState), Wi
     // }
 }
 
+/// Press `Action::Move` towards `direction`, as a player would with a stick.
+pub fn move_towards(action_state: &mut ActionState<crate::actions::Action>, direction: Vec2) {
+    action_state.set_action_data(
+        crate::actions::Action::Move,
+        ActionData {
+            state: leafwing_input_manager::buttonlike::ButtonState::Pressed,
+            value: 1.,
+            timing: leafwing_input_manager::action_state::Timing::default(),
+            consumed: false,
+            axis_pair: Some(DualAxisData::from_xy(direction)),
+        },
+    );
+}
+
 pub fn chase_action_system(
-    transform: Query<&Transform>,
+    game: Res<Game>,
     mut action_states: Query<(
         &mut ActionState<crate::actions::Action>,
         &Chaser,
         &Transform,
+        &Path,
     )>,
     mut query: Query<(&Actor, &mut BBActionState), With<Chase>>,
 ) {
     for (Actor(actor), mut state) in &mut query {
-        if let Ok((mut action_state, chaser, self_transform)) = action_states.get_mut(*actor) {
-            let Some(target) = chaser.target else {
+        if let Ok((mut action_state, chaser, self_transform, path)) = action_states.get_mut(*actor)
+        {
+            if *state == BBActionState::Cancelled {
+                action_state.release(crate::actions::Action::Move);
+                *state = BBActionState::Failure;
+                continue;
+            }
+
+            let Some(position) = chaser.position else {
                 continue;
             };
 
-            let Ok(target_transform) = transform.get(target) else {
+            // Follow the path, and stand still if there is no way there.
+            let Some(next) = path.next(&game, position) else {
+                action_state.release(crate::actions::Action::Move);
                 continue;
             };
 
-            let vector = (target_transform.translation - self_transform.translation)
-                .normalize()
-                .xz();
-
-            match *state {
-                BBActionState::Requested => {
-                    action_state.set_action_data(
-                        crate::actions::Action::Move,
-                        ActionData {
-                            state: leafwing_input_manager::buttonlike::ButtonState::Pressed,
-                            value: 1.,
-                            timing: leafwing_input_manager::action_state::Timing::default(),
-                            consumed: false,
-                            axis_pair: Some(DualAxisData::from_xy(vector)),
-                        },
-                    );
-                    // TODO: set target, timer, and move there, _then_ set success
-                    // *state = BBActionState::Success;
-                }
-                BBActionState::Cancelled => {
-                    *state = BBActionState::Failure;
-                }
-                _ => {}
+            let vector = (next - self_transform.translation).xz().normalize_or_zero();
+
+            if *state == BBActionState::Requested {
+                move_towards(&mut action_state, vector);
+                // TODO: set target, timer, and move there, _then_ set success
+                // *state = BBActionState::Success;
             }
         }
     }
 }
 
+/// Alert while the mob senses its target, and less so the longer it hasn't.
+/// A mob pulled too far from its spawn is fully alert until it is back.
+/// Targets that have since become friendly are left alone, and fleeing mobs
+/// keep going where `Flee` sends them.
 pub fn alert_scorer_system(
-    mut chaser: Query<&mut Chaser>,
-    mut query: Query<(&Actor, &mut Score), With<Alert>>,
-    transforms: Query<(Entity, &Transform, &Faction)>,
+    relations: Res<FactionRelations>,
+    mut chasers: Query<(
+        &Faction,
+        &Perception,
+        &Memory,
+        Option<&Leash>,
+        Has<Fleeing>,
+        Option<&ScoreWeights>,
+        &mut Chaser,
+    )>,
+    factions: Query<&Faction>,
+    mut query: Query<(&Actor, &mut Score, &Alert)>,
 ) {
-    let chase_threshold = 8.0;
-
-    for (Actor(actor), mut score) in &mut query {
-        let mut nearest_distance = f32::MAX;
-        let mut nearest_entity = None;
-
-        let chaser = chaser.get_mut(*actor);
-
-        if let Ok(chaser) = &chaser {
-            if let Some(target) = chaser.target {
-                if let Ok((_, transform, _)) = transforms.get(target) {
-                    nearest_distance = transform.translation.distance(Vec3::ZERO);
-                    nearest_entity = Some(target);
-                    if nearest_distance < chase_threshold {
-                        continue;
-                    }
-                }
-            }
-        }
+    for (Actor(actor), mut score, alert) in &mut query {
+        let Ok((faction, perception, memory, leash, fleeing, weights, mut chaser)) =
+            chasers.get_mut(*actor)
+        else {
+            score.set(0.0);
+            continue;
+        };
 
-        if let Ok((_, self_transform, Faction(self_faction))) = transforms.get(*actor) {
-            for (entity, transform, _) in transforms
-                .iter()
-                .filter(|(_, _, Faction(faction))| faction != self_faction)
-                .filter(|(entity, _, _)| entity != actor)
-            {
-                let distance = transform.translation.distance(self_transform.translation);
-                if distance < nearest_distance {
-                    nearest_distance = distance;
-                    nearest_entity = Some(entity);
-                }
-            }
+        // `Flee` is steering the chaser.
+        if fleeing {
+            score.set(weighted(
+                weights,
+                ScorerKind::Alert,
+                alert.then,
+                memory.certainty(perception),
+            ));
+            continue;
         }
 
-        if let Ok(mut chaser) = chaser {
-            chaser.target = nearest_entity;
+        if let Some(leash) = leash.filter(|leash| leash.returning) {
+            chaser.target = None;
+            chaser.position = Some(leash.home);
+            score.set(weighted(weights, ScorerKind::Alert, alert.then, 1.0));
+            continue;
         }
 
-        if nearest_entity.is_some() && nearest_distance < 8.0 {
-            score.set(1.0);
-        } else {
+        let friendly = memory.target.is_some_and(|target| {
+            factions
+                .get(target)
+                .is_ok_and(|other| relations.get(*faction, *other) == Relation::Friendly)
+        });
+        if friendly {
+            chaser.target = None;
+            chaser.position = None;
             score.set(0.0);
-        };
+            continue;
+        }
+
+        chaser.target = memory.target;
+        chaser.position = memory.last_seen;
+        score.set(weighted(
+            weights,
+            ScorerKind::Alert,
+            alert.then,
+            memory.certainty(perception),
+        ));
     }
 }
-
-//  TODO: MeleeAttacker
-// "MeleeAttacker" is generic over the type of attack.
-// It has a target, and a cooldown.
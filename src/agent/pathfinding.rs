@@ -0,0 +1,263 @@
+//! Grid A* over the board, so mobs find their way around walls, pits and
+//! steps too high for their controller to climb.
+//!
+//! Each mob keeps its `Path` as a cache and only plans again when its target
+//! moves to another cell or the mob strays from the path. Planning is spread
+//! over frames with `MAX_SEARCHES_PER_FRAME`.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_rapier3d::prelude::*;
+
+use super::Chaser;
+use crate::{
+    board::{CellKind, GridPosition},
+    Game,
+};
+
+const MAX_SEARCHES_PER_FRAME: usize = 4;
+/// Searches that expand more cells than this give up.
+const MAX_EXPANDED: usize = 4096;
+
+type GridCell = (usize, usize);
+
+/// Where a mob is headed, and the cells on the way there.
+#[derive(Component, Default, Debug)]
+pub struct Path {
+    /// The cell the path was planned to.
+    pub goal: Option<GridCell>,
+    /// Cells still to walk through, the goal last.
+    pub waypoints: VecDeque<GridCell>,
+    /// Whether the goal could be reached at all when planned.
+    pub reachable: bool,
+}
+
+/// The cost of walking onto a kind of cell, or `None` if it can't be.
+/// Mobs go around what hurts them when they can.
+fn cost(kind: CellKind) -> Option<usize> {
+    match kind {
+        CellKind::Wall | CellKind::Pit => None,
+        CellKind::Floor | CellKind::Ice => Some(1),
+        CellKind::Water => Some(3),
+        CellKind::Lava => Some(10),
+    }
+}
+
+fn distance((ai, aj): GridCell, (bi, bj): GridCell) -> usize {
+    ai.abs_diff(bi) + aj.abs_diff(bj)
+}
+
+/// The highest step a controller climbs on its own.
+pub fn max_step(controller: &KinematicCharacterController, collider: Option<&Collider>) -> f32 {
+    match controller.autostep.map(|autostep| autostep.max_height) {
+        Some(CharacterLength::Absolute(height)) => height,
+        Some(CharacterLength::Relative(ratio)) => {
+            let height = collider.map_or(1.0, |collider| {
+                let aabb = collider.raw.compute_local_aabb();
+                aabb.maxs.y - aabb.mins.y
+            });
+            ratio * height
+        }
+        None => 0.0,
+    }
+}
+
+/// The cells from `start` to `goal`, without `start`, or `None` if there is
+/// no way there.
+pub fn find_path(
+    game: &Game,
+    start: GridCell,
+    goal: GridCell,
+    max_step: f32,
+) -> Option<VecDeque<GridCell>> {
+    let top = |cell| game.cell_to_world(cell).map(|position| position.y);
+
+    let mut open = BinaryHeap::new();
+    let mut came_from: HashMap<GridCell, GridCell> = HashMap::new();
+    let mut best: HashMap<GridCell, usize> = HashMap::new();
+
+    open.push(Reverse((distance(start, goal), 0, start)));
+    best.insert(start, 0);
+
+    let mut expanded = 0;
+    while let Some(Reverse((_, so_far, cell))) = open.pop() {
+        if cell == goal {
+            let mut path = VecDeque::new();
+            let mut cell = goal;
+            while cell != start {
+                path.push_front(cell);
+                cell = came_from[&cell];
+            }
+            return Some(path);
+        }
+
+        if best.get(&cell).is_some_and(|best| *best < so_far) {
+            continue;
+        }
+
+        expanded += 1;
+        if expanded > MAX_EXPANDED {
+            return None;
+        }
+
+        let height = top(cell)?;
+        for next in game.neighbours(cell) {
+            let Some(step) = game.cell(next).and_then(|next| cost(next.kind)) else {
+                continue;
+            };
+            if top(next).is_none_or(|next| next - height > max_step) {
+                continue;
+            }
+
+            let cost = so_far + step;
+            if best.get(&next).is_none_or(|best| cost < *best) {
+                best.insert(next, cost);
+                came_from.insert(next, cell);
+                open.push(Reverse((cost + distance(next, goal), cost, next)));
+            }
+        }
+    }
+
+    None
+}
+
+impl Path {
+    /// Plan paths to where each chaser thinks its target is, when that has
+    /// changed cell or the chaser is no longer on its path.
+    pub fn plan_system(
+        game: Res<Game>,
+        mut chasers: Query<(
+            &Chaser,
+            &GridPosition,
+            &KinematicCharacterController,
+            Option<&Collider>,
+            &mut Path,
+        )>,
+    ) {
+        let mut searches = 0;
+
+        for (chaser, position, controller, collider, mut path) in &mut chasers {
+            let Some(goal) = chaser
+                .position
+                .and_then(|position| game.world_to_cell(position))
+            else {
+                continue;
+            };
+
+            if let Some(reached) = path.waypoints.iter().position(|cell| *cell == position.0) {
+                path.waypoints.drain(..=reached);
+            }
+
+            let on_path = path
+                .waypoints
+                .front()
+                .is_none_or(|next| game.neighbours(position.0).any(|cell| cell == *next));
+            if path.goal == Some(goal) && on_path {
+                continue;
+            }
+
+            if searches == MAX_SEARCHES_PER_FRAME {
+                continue;
+            }
+            searches += 1;
+
+            let found = find_path(&game, position.0, goal, max_step(controller, collider));
+            path.goal = Some(goal);
+            path.reachable = found.is_some();
+            path.waypoints = found.unwrap_or_default();
+        }
+    }
+
+    /// Where to head next: the next waypoint, or `target` once in its cell.
+    /// `None` if there is no way to the target.
+    pub fn next(&self, game: &Game, target: Vec3) -> Option<Vec3> {
+        if !self.reachable {
+            return None;
+        }
+
+        match self.waypoints.front() {
+            Some(cell) => game.cell_to_world(*cell),
+            None => Some(target),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Cell;
+
+    /// A flat board from rows of level symbols, the first row at `j = 0`.
+    fn game(rows: &[&str]) -> Game {
+        Game {
+            board: rows
+                .iter()
+                .map(|row| {
+                    row.chars()
+                        .map(|symbol| Cell {
+                            height: 0.0,
+                            kind: CellKind::from_symbol(symbol).unwrap(),
+                        })
+                        .collect()
+                })
+                .collect(),
+            ..default()
+        }
+    }
+
+    #[test]
+    fn crosses_open_floor_in_a_straight_line() {
+        let game = game(&["....."]);
+        let path = find_path(&game, (0, 0), (4, 0), 0.5).unwrap();
+        assert_eq!(path, [(1, 0), (2, 0), (3, 0), (4, 0)]);
+    }
+
+    #[test]
+    fn goes_around_walls() {
+        let game = game(&[
+            ".#.", //
+            ".#.", //
+            "...",
+        ]);
+        let path = find_path(&game, (0, 0), (2, 0), 0.5).unwrap();
+        assert_eq!(path.len(), 6);
+        assert_eq!(path.back(), Some(&(2, 0)));
+        assert!(path
+            .iter()
+            .all(|cell| game.cell(*cell).unwrap().kind != CellKind::Wall));
+    }
+
+    #[test]
+    fn takes_a_detour_rather_than_lava() {
+        let game = game(&[
+            ".^.", //
+            "...",
+        ]);
+        let path = find_path(&game, (0, 0), (2, 0), 0.5).unwrap();
+        assert!(!path.contains(&(1, 0)));
+    }
+
+    #[test]
+    fn finds_nothing_past_pits() {
+        let game = game(&[".o."]);
+        assert_eq!(find_path(&game, (0, 0), (2, 0), 0.5), None);
+    }
+
+    #[test]
+    fn does_not_climb_steps_higher_than_max_step() {
+        let mut game = game(&["..."]);
+        game.board[0][1].height = 1.0;
+
+        assert_eq!(find_path(&game, (0, 0), (2, 0), 0.5), None);
+        assert!(find_path(&game, (0, 0), (2, 0), 1.5).is_some());
+    }
+
+    #[test]
+    fn an_empty_path_to_where_it_already_is() {
+        let game = game(&["..."]);
+        assert_eq!(find_path(&game, (1, 0), (1, 0), 0.5), Some(VecDeque::new()));
+    }
+}
@@ -0,0 +1,178 @@
+//! What mobs know about their enemies.
+//!
+//! A mob sees enemies within its sight range and field of view, as long as no
+//! wall is in the way, and hears those within its hearing radius whichever way
+//! it faces. Anyone not friendly according to `FactionRelations` counts as
+//! an enemy here. Which of the enemies it senses becomes its target is up to
+//! `Threat::system`. It remembers where it last sensed its target for a while
+//! after losing it, which `alert_scorer_system` uses to give up gradually.
+//! Mobs can also learn of a target from allies that sense it, see `squad`.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::Deserialize;
+
+use super::Health;
+use crate::{Faction, FactionRelations, Relation};
+
+/// Rays are cast between points this high above the feet.
+pub const EYE_HEIGHT: f32 = 1.0;
+/// How quickly the estimate of the target's velocity follows what is sensed.
+const VELOCITY_SMOOTHING: f32 = 0.3;
+
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Perception {
+    pub sight_range: f32,
+    /// The whole width of the vision cone, in degrees.
+    pub field_of_view: f32,
+    pub hearing: f32,
+    /// Seconds before a target that can't be sensed any more is forgotten.
+    pub memory: f32,
+    /// How far away friendly mobs hear about a sensed target.
+    pub callout: f32,
+}
+
+impl Default for Perception {
+    fn default() -> Self {
+        Self {
+            sight_range: 10.0,
+            field_of_view: 120.0,
+            hearing: 3.0,
+            memory: 4.0,
+            callout: 8.0,
+        }
+    }
+}
+
+/// The enemy a mob is aware of.
+#[derive(Component, Default, Clone, Debug)]
+pub struct Memory {
+    pub target: Option<Entity>,
+    /// Where the target was when last sensed.
+    pub last_seen: Option<Vec3>,
+    /// Whether the target is sensed right now.
+    pub sensed: bool,
+    /// Seconds since the target was last sensed.
+    pub age: f32,
+    /// How fast the target seemed to move while sensed.
+    pub velocity: Vec3,
+    /// Every enemy sensed right now, and where. Neutral characters are
+    /// included, so mobs can fight back against them.
+    pub contacts: Vec<(Entity, Vec3)>,
+}
+
+impl Memory {
+    /// How sure the mob still is about its target: 1.0 while sensed, down to
+    /// 0.0 when it is forgotten.
+    pub fn certainty(&self, perception: &Perception) -> f32 {
+        if self.target.is_none() {
+            return 0.0;
+        }
+        (1.0 - self.age / perception.memory.max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+
+    /// Where `enemy` is, if it is sensed right now.
+    pub fn contact(&self, enemy: Entity) -> Option<Vec3> {
+        self.contacts
+            .iter()
+            .find(|(contact, _)| *contact == enemy)
+            .map(|(_, position)| *position)
+    }
+
+    /// Make `target` the target, last seen at `position`.
+    pub fn remember(&mut self, target: Entity, position: Vec3) {
+        self.target = Some(target);
+        self.last_seen = Some(position);
+        self.sensed = self.contact(target).is_some();
+        self.age = 0.0;
+        self.velocity = Vec3::ZERO;
+    }
+
+    /// Drop the target, but not the contacts.
+    pub fn forget(&mut self) {
+        self.target = None;
+        self.last_seen = None;
+        self.sensed = false;
+        self.age = 0.0;
+        self.velocity = Vec3::ZERO;
+    }
+}
+
+/// Whether no wall is in the way between `from` and `to`.
+pub fn line_of_sight(rapier: &RapierContext, from: Vec3, to: Vec3) -> bool {
+    let offset = to - from;
+    let distance = offset.length();
+    rapier
+        .cast_ray(
+            from,
+            offset / distance.max(f32::EPSILON),
+            distance,
+            true,
+            QueryFilter::only_fixed().exclude_sensors(),
+        )
+        .is_none()
+}
+
+impl Perception {
+    /// Whether `target` is in view from `eye`, looking along `forward`.
+    fn sees(&self, rapier: &RapierContext, eye: Vec3, forward: Vec3, target: Vec3) -> bool {
+        let to_target = target - eye;
+        let distance = to_target.length();
+        if distance > self.sight_range {
+            return false;
+        }
+
+        let angle = forward.angle_between(to_target).to_degrees();
+        if angle > self.field_of_view / 2.0 {
+            return false;
+        }
+
+        line_of_sight(rapier, eye, target)
+    }
+
+    pub fn system(
+        rapier: Res<RapierContext>,
+        relations: Res<FactionRelations>,
+        mut mobs: Query<(Entity, &Transform, &Faction, &Perception, &mut Memory)>,
+        enemies: Query<(Entity, &Transform, &Faction), With<Health>>,
+        time: Res<Time>,
+    ) {
+        for (entity, transform, faction, perception, mut memory) in &mut mobs {
+            let eye = transform.translation + Vec3::Y * EYE_HEIGHT;
+            let forward = transform.forward();
+
+            memory.contacts = enemies
+                .iter()
+                .filter(|(enemy, _, other)| {
+                    *enemy != entity && relations.get(*faction, **other) != Relation::Friendly
+                })
+                .map(|(enemy, enemy_transform, _)| (enemy, enemy_transform.translation))
+                .filter(|(_, position)| {
+                    position.distance(transform.translation) <= perception.hearing
+                        || perception.sees(&rapier, eye, forward, *position + Vec3::Y * EYE_HEIGHT)
+                })
+                .collect();
+
+            let Some(target) = memory.target else {
+                continue;
+            };
+
+            if let Some(position) = memory.contact(target) {
+                let delta = time.delta_seconds();
+                if let Some(last_seen) = memory.last_seen.filter(|_| memory.sensed && delta > 0.0) {
+                    let velocity = (position - last_seen) / delta;
+                    memory.velocity = memory.velocity.lerp(velocity, VELOCITY_SMOOTHING);
+                }
+                memory.last_seen = Some(position);
+                memory.sensed = true;
+                memory.age = 0.0;
+            } else if memory.age > perception.memory || !enemies.contains(target) {
+                memory.forget();
+            } else {
+                memory.sensed = false;
+                memory.age += time.delta_seconds();
+            }
+        }
+    }
+}
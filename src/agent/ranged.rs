@@ -0,0 +1,408 @@
+//! Mobs that fight from a distance.
+//!
+//! A ranged mob `Skirmish`es: it keeps between `min_distance` and
+//! `max_distance` from its target, strafing from side to side, and fires a
+//! projectile whenever it can see the target and its cooldown is up. It aims
+//! where the target will be by the time the projectile gets there, going by
+//! how fast the target seemed to move. A target closer than
+//! `retreat_distance` makes it stop shooting and `Retreat`.
+
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use big_brain::actions::ActionState as BBActionState;
+use big_brain::prelude::*;
+use leafwing_input_manager::action_state::ActionState;
+use serde::Deserialize;
+use std::time::Duration;
+
+use super::{
+    line_of_sight, move_towards, weighted, ActionKind, Chaser, Fleeing, Memory, Path, ScoreWeights,
+    ScorerKind, EYE_HEIGHT,
+};
+use crate::{
+    actions::{
+        attack::{AttackBundle, Effect},
+        conditions::{Condition, Locked},
+        Action,
+    },
+    Faction, Game,
+};
+
+pub const PROJECTILE_RADIUS: f32 = 0.15;
+/// Height above the feet projectiles fly at.
+pub const PROJECTILE_HEIGHT: f32 = 0.75;
+pub const PROJECTILE_KNOCKBACK: f32 = 2.0;
+/// How far ahead to check the ground is safe before stepping there.
+const LOOKAHEAD: f32 = 0.8;
+
+#[derive(Debug, Clone, Component, ScorerBuilder)]
+pub struct Crowded {
+    /// The action its choice leads to, which the choice's weight is for.
+    pub then: ActionKind,
+}
+
+#[derive(Default, Debug, Clone, Component, ActionBuilder)]
+pub struct Skirmish {
+    /// Which way to strafe, 1.0 or -1.0.
+    pub side: f32,
+    pub timer: f32,
+}
+
+#[derive(Default, Debug, Clone, Component, ActionBuilder)]
+pub struct Retreat;
+
+/// How a kind of ranged mob keeps its distance and shoots.
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Ranged {
+    pub min_distance: f32,
+    pub max_distance: f32,
+    /// Run from targets closer than this.
+    pub retreat_distance: f32,
+    /// Seconds before strafing the other way.
+    pub strafe_interval: f32,
+    /// Strafing speed, as a fraction of full speed.
+    pub strafe_speed: f32,
+    /// How far projectiles fly.
+    pub range: f32,
+    pub projectile_speed: f32,
+    pub damage: f32,
+    /// Seconds between shots.
+    pub cooldown: f32,
+    #[serde(skip)]
+    pub reload: Timer,
+}
+
+impl Default for Ranged {
+    fn default() -> Self {
+        Self {
+            min_distance: 4.0,
+            max_distance: 7.0,
+            retreat_distance: 2.5,
+            strafe_interval: 1.5,
+            strafe_speed: 0.6,
+            range: 9.0,
+            projectile_speed: 8.0,
+            damage: 1.0,
+            cooldown: 1.5,
+            reload: Timer::default(),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ProjectileAssets {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for ProjectileAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(
+            shape::UVSphere {
+                radius: PROJECTILE_RADIUS,
+                ..default()
+            }
+            .into(),
+        );
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                base_color: Color::ORANGE_RED,
+                emissive: Color::ORANGE_RED,
+                ..default()
+            });
+        Self { mesh, material }
+    }
+}
+
+/// Where to aim from `from` to hit something at `target`, moving at
+/// `velocity`, with a projectile flying at `speed`. Straight at `target` if
+/// it can't be caught.
+pub fn lead(from: Vec3, target: Vec3, velocity: Vec3, speed: f32) -> Vec3 {
+    let offset = target - from;
+
+    // Solve |offset + velocity * t| = speed * t for the earliest t > 0.
+    let a = velocity.length_squared() - speed * speed;
+    let b = 2.0 * offset.dot(velocity);
+    let c = offset.length_squared();
+
+    let time = if a.abs() < f32::EPSILON {
+        (b.abs() >= f32::EPSILON).then_some(-c / b)
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        (discriminant >= 0.0).then(|| {
+            let root = discriminant.sqrt();
+            let early = (-b - root) / (2.0 * a);
+            let late = (-b + root) / (2.0 * a);
+            if early > 0.0 && (late <= 0.0 || early < late) {
+                early
+            } else {
+                late
+            }
+        })
+    };
+
+    match time {
+        Some(time) if time > 0.0 => target + velocity * time,
+        _ => target,
+    }
+}
+
+/// `direction`, or the closest to it of a few turns either way, that keeps
+/// on safe ground. Zero if there is none.
+fn safe_direction(game: &Game, position: Vec3, direction: Vec2) -> Vec2 {
+    if direction == Vec2::ZERO {
+        return Vec2::ZERO;
+    }
+
+    [0.0_f32, 45.0, -45.0, 90.0, -90.0]
+        .into_iter()
+        .map(|angle| Vec2::from_angle(angle.to_radians()).rotate(direction))
+        .find(|turned| {
+            let ahead = position + Vec3::new(turned.x, 0.0, turned.y).normalize() * LOOKAHEAD;
+            game.world_to_cell(ahead)
+                .and_then(|cell| game.cell(cell))
+                .is_some_and(|cell| cell.kind.is_safe())
+        })
+        .unwrap_or(Vec2::ZERO)
+}
+
+/// Crowded while a sensed target is within `retreat_distance`.
+pub fn crowded_scorer_system(
+    mobs: Query<(&Transform, &Ranged, &Memory, Option<&ScoreWeights>)>,
+    mut query: Query<(&Actor, &mut Score, &Crowded)>,
+) {
+    for (Actor(actor), mut score, scorer) in &mut query {
+        let Ok((transform, ranged, memory, weights)) = mobs.get(*actor) else {
+            score.set(0.0);
+            continue;
+        };
+
+        let crowded = memory.sensed
+            && memory.last_seen.is_some_and(|target| {
+                target.xz().distance(transform.translation.xz()) < ranged.retreat_distance
+            });
+        let value = if crowded { 1.0 } else { 0.0 };
+        score.set(weighted(weights, ScorerKind::Crowded, scorer.then, value));
+    }
+}
+
+/// Keep within the distance band of the target, strafing around it.
+pub fn skirmish_action_system(
+    game: Res<Game>,
+    mut mobs: Query<(
+        &mut ActionState<Action>,
+        &Transform,
+        &Ranged,
+        &Chaser,
+        &Path,
+    )>,
+    mut query: Query<(&Actor, &mut BBActionState, &mut Skirmish)>,
+    time: Res<Time>,
+) {
+    for (Actor(actor), mut state, mut skirmish) in &mut query {
+        let Ok((mut action_state, transform, ranged, chaser, path)) = mobs.get_mut(*actor) else {
+            continue;
+        };
+
+        match *state {
+            BBActionState::Requested => {
+                skirmish.side = 1.0;
+                skirmish.timer = ranged.strafe_interval;
+                *state = BBActionState::Executing;
+            }
+            BBActionState::Cancelled => {
+                action_state.release(Action::Move);
+                *state = BBActionState::Failure;
+                continue;
+            }
+            BBActionState::Executing => {}
+            _ => continue,
+        }
+
+        skirmish.timer -= time.delta_seconds();
+        if skirmish.timer <= 0.0 {
+            skirmish.side = -skirmish.side;
+            skirmish.timer = ranged.strafe_interval;
+        }
+
+        let Some(target) = chaser.position else {
+            action_state.release(Action::Move);
+            continue;
+        };
+
+        let offset = (target - transform.translation).xz();
+        let distance = offset.length();
+        let toward = offset.normalize_or_zero();
+        let strafe = toward.perp() * skirmish.side * ranged.strafe_speed;
+
+        // Too far: follow the path in, still weaving a little.
+        if distance > ranged.max_distance {
+            let Some(next) = path.next(&game, target) else {
+                action_state.release(Action::Move);
+                continue;
+            };
+            let seek = (next - transform.translation).xz().normalize_or_zero();
+            move_towards(&mut action_state, (seek + strafe * 0.5).normalize_or_zero());
+            continue;
+        }
+
+        let back_off = if distance < ranged.min_distance {
+            -toward
+        } else {
+            Vec2::ZERO
+        };
+        let direction = (back_off + strafe).clamp_length_max(1.0);
+        let safe = safe_direction(&game, transform.translation, direction) * direction.length();
+        move_towards(&mut action_state, safe);
+    }
+}
+
+/// Back away from the target without shooting until it is at `min_distance`.
+pub fn retreat_action_system(
+    game: Res<Game>,
+    mut mobs: Query<(&mut ActionState<Action>, &Transform, &Ranged, &Chaser)>,
+    mut query: Query<(&Actor, &mut BBActionState), With<Retreat>>,
+) {
+    for (Actor(actor), mut state) in &mut query {
+        let Ok((mut action_state, transform, ranged, chaser)) = mobs.get_mut(*actor) else {
+            continue;
+        };
+
+        match *state {
+            BBActionState::Requested => *state = BBActionState::Executing,
+            BBActionState::Cancelled => {
+                action_state.release(Action::Move);
+                *state = BBActionState::Failure;
+                continue;
+            }
+            BBActionState::Executing => {}
+            _ => continue,
+        }
+
+        let Some(target) = chaser.position else {
+            action_state.release(Action::Move);
+            *state = BBActionState::Success;
+            continue;
+        };
+
+        let away = (transform.translation - target).xz();
+        if away.length() >= ranged.min_distance {
+            action_state.release(Action::Move);
+            *state = BBActionState::Success;
+            continue;
+        }
+
+        let direction = safe_direction(&game, transform.translation, away.normalize_or_zero());
+        move_towards(&mut action_state, direction);
+    }
+}
+
+impl Ranged {
+    /// Fire at the target when it is in range and in sight.
+    pub fn fire_system(
+        mut commands: Commands,
+        rapier: Res<RapierContext>,
+        assets: Res<ProjectileAssets>,
+        mut mobs: Query<
+            (Entity, &mut Transform, &Faction, &Memory, &mut Ranged),
+            (Without<Condition<Locked>>, Without<Fleeing>),
+        >,
+        time: Res<Time>,
+    ) {
+        for (entity, mut transform, faction, memory, mut ranged) in &mut mobs {
+            ranged.reload.tick(time.delta());
+            if !ranged.reload.finished() || !memory.sensed {
+                continue;
+            }
+            let Some(target) = memory.last_seen else {
+                continue;
+            };
+
+            let distance = target.xz().distance(transform.translation.xz());
+            if distance < ranged.retreat_distance || distance > ranged.range {
+                continue;
+            }
+
+            let eye = transform.translation + Vec3::Y * EYE_HEIGHT;
+            if !line_of_sight(&rapier, eye, target + Vec3::Y * EYE_HEIGHT) {
+                continue;
+            }
+
+            let from = transform.translation + Vec3::Y * PROJECTILE_HEIGHT;
+            let velocity = Vec3::new(memory.velocity.x, 0.0, memory.velocity.z);
+            let aim = lead(
+                from,
+                target + Vec3::Y * PROJECTILE_HEIGHT,
+                velocity,
+                ranged.projectile_speed,
+            );
+            let direction = Vec3::new(aim.x - from.x, 0.0, aim.z - from.z).normalize_or_zero();
+            if direction == Vec3::ZERO {
+                continue;
+            }
+
+            transform.rotation = crate::utils::look_to(direction);
+            ranged.reload = Timer::from_seconds(ranged.cooldown, TimerMode::Once);
+
+            let flight = Duration::from_secs_f32(ranged.range / ranged.projectile_speed);
+            let projectile = AttackBundle::new(
+                Effect::new(ranged.damage, vec![], direction * PROJECTILE_KNOCKBACK),
+                Collider::ball(PROJECTILE_RADIUS),
+                *faction,
+                flight,
+                Transform::from_translation(from),
+            )
+            .with_source(entity)
+            .with_velocity(direction * ranged.projectile_speed);
+
+            commands.spawn(projectile).insert((
+                assets.mesh.clone(),
+                assets.material.clone(),
+                Visibility::default(),
+                ComputedVisibility::default(),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aims_straight_at_a_still_target() {
+        let target = Vec3::new(4.0, 0.0, 3.0);
+        assert_eq!(lead(Vec3::ZERO, target, Vec3::ZERO, 6.0), target);
+    }
+
+    #[test]
+    fn aims_where_projectile_and_target_meet() {
+        let target = Vec3::new(5.0, 0.0, 0.0);
+        let velocity = Vec3::new(0.0, 0.0, 2.0);
+        let aim = lead(Vec3::ZERO, target, velocity, 6.0);
+
+        // The projectile gets there just as the target does.
+        let flight = aim.length() / 6.0;
+        let walk = aim.distance(target) / velocity.length();
+        assert!((flight - walk).abs() < 1e-4);
+        assert!(aim.z > 0.0);
+    }
+
+    #[test]
+    fn aims_at_a_target_it_cannot_catch() {
+        let target = Vec3::new(5.0, 0.0, 0.0);
+        let velocity = Vec3::new(10.0, 0.0, 0.0);
+        assert_eq!(lead(Vec3::ZERO, target, velocity, 6.0), target);
+    }
+
+    #[test]
+    fn leads_a_target_as_fast_as_the_projectile_coming_closer() {
+        let target = Vec3::new(6.0, 0.0, 0.0);
+        let velocity = Vec3::new(-6.0, 0.0, 0.0);
+        let aim = lead(Vec3::ZERO, target, velocity, 6.0);
+        assert!((aim - Vec3::new(3.0, 0.0, 0.0)).length() < 1e-4);
+    }
+}
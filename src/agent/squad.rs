@@ -0,0 +1,332 @@
+//! Mobs fighting together.
+//!
+//! A mob that senses its target calls it out to friendly mobs within its
+//! `callout` radius. Those that aren't after anything else take it up and head
+//! for where it was seen, even if they can't sense it themselves.
+//!
+//! Mobs with `Coordination` that go after the same target spread out around
+//! it. Only the first few get an `AttackSlot` and close in to attack, each
+//! from its own side. The others circle the target out of reach until a slot
+//! frees up.
+
+use std::f32::consts::TAU;
+
+use bevy::ecs::query::Has;
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+use super::{Chaser, Fleeing, Leash, Memory, Perception, Threat};
+use crate::{Faction, FactionRelations, Game, Relation};
+
+/// Threat a called out target starts with, if the mob had none for it.
+const CALLOUT_THREAT: f32 = 0.5;
+/// How far ahead of its place on the ring a circling mob heads, in radians,
+/// which keeps it moving around the target.
+const ORBIT_LEAD: f32 = 0.4;
+
+/// How a kind of mob shares its target with others.
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Coordination {
+    /// How many mobs may attack the same target at once, counting this one.
+    pub attackers: usize,
+    /// How far from the target mobs without a slot circle.
+    pub circle_distance: f32,
+    /// How close to the target attackers head for, from their own side.
+    pub attack_distance: f32,
+}
+
+impl Default for Coordination {
+    fn default() -> Self {
+        Self {
+            attackers: 2,
+            circle_distance: 3.5,
+            attack_distance: 0.8,
+        }
+    }
+}
+
+/// On mobs allowed to attack their target right now.
+#[derive(Component, Clone, Copy, Debug)]
+#[component(storage = "SparseSet")]
+pub struct AttackSlot;
+
+/// A mob after a target, as seen by its group.
+struct Member {
+    entity: Entity,
+    position: Vec3,
+    /// Angle around the target.
+    bearing: f32,
+    distance: f32,
+    attackers: usize,
+    slot: bool,
+}
+
+/// Whether `position` is on safe ground.
+fn is_safe(game: &Game, position: Vec3) -> bool {
+    game.world_to_cell(position)
+        .and_then(|cell| game.cell(cell))
+        .is_some_and(|cell| cell.kind.is_safe())
+}
+
+/// Hand out attack slots within a group: mobs already attacking keep at it,
+/// then the closest go first. The mobs whose slot changed, with whether they
+/// now have one.
+fn assign_slots(members: &mut [Member]) -> Vec<(Entity, bool)> {
+    members.sort_by(|a, b| b.slot.cmp(&a.slot).then(a.distance.total_cmp(&b.distance)));
+    let mut given = 0;
+    let mut changed = Vec::new();
+    for member in members {
+        let attacking = given < member.attackers;
+        if attacking {
+            given += 1;
+        }
+        if attacking != member.slot {
+            member.slot = attacking;
+            changed.push((member.entity, attacking));
+        }
+    }
+    changed
+}
+
+/// Pass sensed targets on to friendly mobs that aren't after anything else.
+pub fn callout_system(
+    relations: Res<FactionRelations>,
+    mut mobs: Query<(
+        Entity,
+        &Transform,
+        &Faction,
+        &Perception,
+        &mut Memory,
+        &mut Threat,
+        Option<&Leash>,
+    )>,
+) {
+    let callouts: Vec<_> = mobs
+        .iter()
+        .filter_map(|(entity, transform, faction, perception, memory, ..)| {
+            let target = memory.target.filter(|_| memory.sensed)?;
+            Some((
+                entity,
+                transform.translation,
+                *faction,
+                perception.callout,
+                target,
+                memory.last_seen?,
+            ))
+        })
+        .collect();
+
+    if callouts.is_empty() {
+        return;
+    }
+
+    for (entity, transform, faction, _, mut memory, mut threat, leash) in &mut mobs {
+        if memory.sensed || leash.is_some_and(|leash| leash.returning) {
+            continue;
+        }
+
+        let heard = callouts
+            .iter()
+            .filter(|(caller, position, caller_faction, radius, target, _)| {
+                *caller != entity
+                    && memory.target.is_none_or(|current| current == *target)
+                    && relations.get(*faction, *caller_faction) == Relation::Friendly
+                    && position.distance(transform.translation) <= *radius
+            })
+            .min_by(|(_, a, ..), (_, b, ..)| {
+                a.distance(transform.translation)
+                    .total_cmp(&b.distance(transform.translation))
+            });
+        let Some((_, _, _, _, target, last_seen)) = heard else {
+            continue;
+        };
+
+        if memory.target == Some(*target) {
+            memory.last_seen = Some(*last_seen);
+            memory.age = 0.0;
+        } else {
+            memory.remember(*target, *last_seen);
+        }
+
+        threat.target = Some(*target);
+        let value = threat.table.entry(*target).or_default();
+        *value = value.max(CALLOUT_THREAT);
+    }
+}
+
+impl Coordination {
+    /// Hand out attack slots and place every mob of a group around its target.
+    pub fn system(
+        mut commands: Commands,
+        game: Res<Game>,
+        mut mobs: Query<
+            (
+                Entity,
+                &Transform,
+                &Coordination,
+                &mut Chaser,
+                Has<AttackSlot>,
+                Option<&Leash>,
+            ),
+            Without<Fleeing>,
+        >,
+        fleeing: Query<Entity, (With<AttackSlot>, With<Fleeing>)>,
+        targets: Query<&Transform>,
+    ) {
+        for entity in &fleeing {
+            commands.entity(entity).remove::<AttackSlot>();
+        }
+
+        let mut groups: HashMap<Entity, (Vec3, Vec<Member>)> = HashMap::new();
+        for (entity, transform, coordination, chaser, slot, leash) in &mobs {
+            let target = chaser
+                .target
+                .filter(|_| !leash.is_some_and(|leash| leash.returning))
+                .and_then(|target| {
+                    targets
+                        .get(target)
+                        .ok()
+                        .map(|transform| (target, transform.translation))
+                });
+            let Some((target, centre)) = target else {
+                if slot {
+                    commands.entity(entity).remove::<AttackSlot>();
+                }
+                continue;
+            };
+
+            let offset = (transform.translation - centre).xz();
+            groups
+                .entry(target)
+                .or_insert_with(|| (centre, Vec::new()))
+                .1
+                .push(Member {
+                    entity,
+                    position: transform.translation,
+                    bearing: offset.y.atan2(offset.x),
+                    distance: offset.length(),
+                    attackers: coordination.attackers,
+                    slot,
+                });
+        }
+
+        for (centre, mut members) in groups.into_values() {
+            for (entity, attacking) in assign_slots(&mut members) {
+                if attacking {
+                    commands.entity(entity).insert(AttackSlot);
+                } else {
+                    commands.entity(entity).remove::<AttackSlot>();
+                }
+            }
+
+            // Spread evenly around the target, keeping the order the mobs
+            // are already in so they don't cross paths.
+            members.sort_by(|a, b| a.bearing.total_cmp(&b.bearing));
+            let base = members.first().map_or(0.0, |member| member.bearing);
+            let count = members.len() as f32;
+
+            for (n, member) in members.iter().enumerate() {
+                let Ok((.., coordination, mut chaser, _, _)) = mobs.get_mut(member.entity) else {
+                    continue;
+                };
+
+                let angle = base + TAU * n as f32 / count;
+                let (angle, distance) = if member.slot {
+                    (angle, coordination.attack_distance)
+                } else {
+                    (angle + ORBIT_LEAD, coordination.circle_distance)
+                };
+                let side = Vec2::from_angle(angle);
+                let position = centre + Vec3::new(side.x, 0.0, side.y) * distance;
+
+                chaser.position = Some(if is_safe(&game, position) {
+                    position
+                } else if member.slot {
+                    centre
+                } else {
+                    member.position
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(index: u32, distance: f32, slot: bool) -> Member {
+        Member {
+            entity: Entity::from_raw(index),
+            position: Vec3::X * distance,
+            bearing: 0.0,
+            distance,
+            attackers: 2,
+            slot,
+        }
+    }
+
+    fn attacking(members: &[Member]) -> Vec<Entity> {
+        let mut entities: Vec<_> = members
+            .iter()
+            .filter(|member| member.slot)
+            .map(|member| member.entity)
+            .collect();
+        entities.sort();
+        entities
+    }
+
+    #[test]
+    fn the_closest_mobs_attack_first() {
+        let mut members = vec![
+            member(0, 5.0, false),
+            member(1, 1.0, false),
+            member(2, 3.0, false),
+        ];
+
+        let changed = assign_slots(&mut members);
+
+        assert_eq!(
+            attacking(&members),
+            [Entity::from_raw(1), Entity::from_raw(2)]
+        );
+        assert_eq!(changed.len(), 2);
+    }
+
+    #[test]
+    fn attackers_keep_their_slot() {
+        let mut members = vec![
+            member(0, 5.0, true),
+            member(1, 1.0, false),
+            member(2, 3.0, true),
+        ];
+
+        let changed = assign_slots(&mut members);
+
+        assert_eq!(
+            attacking(&members),
+            [Entity::from_raw(0), Entity::from_raw(2)]
+        );
+        assert!(changed.is_empty());
+    }
+
+    #[test]
+    fn slots_beyond_the_limit_are_taken_back() {
+        let mut members = vec![
+            member(0, 1.0, true),
+            member(1, 2.0, true),
+            member(2, 3.0, true),
+        ];
+
+        let changed = assign_slots(&mut members);
+
+        assert_eq!(
+            attacking(&members),
+            [Entity::from_raw(0), Entity::from_raw(1)]
+        );
+        assert_eq!(changed, [(Entity::from_raw(2), false)]);
+    }
+}
@@ -0,0 +1,128 @@
+//! Local steering, blended into the `Action::Move` that `chase_action_system`
+//! sets.
+//!
+//! Chasers keep apart from each other (separation), slow down and stop short
+//! of their target (arrival), and slide along walls in their way (obstacle
+//! avoidance, with a shape cast against the board). A pack chasing the same
+//! target ends up around it rather than on top of each other.
+
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use leafwing_input_manager::{action_state::ActionState, axislike::DualAxisData};
+use serde::Deserialize;
+
+use super::Chaser;
+use crate::actions::Action;
+
+const SEPARATION_WEIGHT: f32 = 1.5;
+/// How far ahead to look for walls.
+const AVOID_DISTANCE: f32 = 0.8;
+/// Height above the feet to cast from, so steps the controller climbs
+/// aren't seen as walls.
+const AVOID_HEIGHT: f32 = 0.8;
+const AVOID_RADIUS: f32 = 0.2;
+
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Steering {
+    /// Other chasers closer than this are pushed away from.
+    pub separation: f32,
+    /// Start slowing down this far from the target.
+    pub arrival: f32,
+    /// Stop this far from the target.
+    pub stop: f32,
+}
+
+impl Default for Steering {
+    fn default() -> Self {
+        Self {
+            separation: 1.2,
+            arrival: 1.5,
+            stop: 0.8,
+        }
+    }
+}
+
+impl Steering {
+    /// How hard to push away from the chasers around `position`.
+    fn separation(
+        &self,
+        entity: Entity,
+        position: Vec2,
+        others: &Query<(Entity, &Transform), With<Steering>>,
+    ) -> Vec2 {
+        others
+            .iter()
+            .filter(|(other, _)| *other != entity)
+            .map(|(_, transform)| position - transform.translation.xz())
+            .filter(|away| away.length() < self.separation)
+            .map(|away| away.normalize_or_zero() * (1.0 - away.length() / self.separation))
+            .sum()
+    }
+
+    /// How much of the full speed to keep at `distance` from the target.
+    fn arrival(&self, distance: f32) -> f32 {
+        ((distance - self.stop) / (self.arrival - self.stop).max(f32::EPSILON)).clamp(0.0, 1.0)
+    }
+
+    pub fn system(
+        rapier: Res<RapierContext>,
+        mut agents: Query<(
+            Entity,
+            &Transform,
+            &Steering,
+            &Chaser,
+            &mut ActionState<Action>,
+        )>,
+        others: Query<(Entity, &Transform), With<Steering>>,
+    ) {
+        let probe = Collider::ball(AVOID_RADIUS);
+
+        for (entity, transform, steering, chaser, mut action_state) in &mut agents {
+            if !action_state.pressed(Action::Move) {
+                continue;
+            }
+            let Some(seek) = action_state.action_data(Action::Move).axis_pair else {
+                continue;
+            };
+
+            let position = transform.translation.xz();
+            let arrival = chaser.position.map_or(1.0, |target| {
+                steering.arrival(target.xz().distance(position))
+            });
+
+            let mut steer = seek.xy() * arrival
+                + steering.separation(entity, position, &others) * SEPARATION_WEIGHT;
+
+            let direction = Vec3::new(steer.x, 0.0, steer.y).normalize_or_zero();
+            if direction != Vec3::ZERO {
+                let hit = rapier.cast_shape(
+                    transform.translation + Vec3::Y * AVOID_HEIGHT,
+                    Quat::IDENTITY,
+                    direction,
+                    &probe,
+                    AVOID_DISTANCE,
+                    QueryFilter::only_fixed(),
+                );
+
+                // Slide along the wall instead of into it.
+                if let Some((_, toi)) = hit {
+                    let mut normal = toi.normal1.xz().normalize_or_zero();
+                    if normal.dot(direction.xz()) > 0.0 {
+                        normal = -normal;
+                    }
+                    let into = steer.dot(normal).min(0.0);
+                    steer -= normal * into;
+                    steer += normal * (1.0 - toi.toi / AVOID_DISTANCE);
+                }
+            }
+
+            let steer = steer.clamp_length_max(1.0);
+            let mut data = action_state.action_data(Action::Move).clone();
+            data.axis_pair = Some(DualAxisData::from_xy(steer));
+            data.value = steer.length();
+            action_state.set_action_data(Action::Move, data);
+        }
+    }
+}
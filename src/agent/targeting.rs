@@ -0,0 +1,288 @@
+//! Which enemy a mob goes after.
+//!
+//! Every mob keeps a threat table. Enemies it senses gain threat the closer
+//! they are, and enemies that hurt it gain threat for the damage dealt. Threat
+//! decays over time. The mob targets whoever has the most, but only switches
+//! away from its current target once another has `switch_ratio` times as much,
+//! so it doesn't flip back and forth between two enemies.
+//!
+//! Only hostile characters gain threat by being close. Neutral ones only do
+//! once they attack, and friendly ones never do.
+//!
+//! Mobs that stray further than their leash from where they spawned drop
+//! everything and walk back home.
+
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+use super::{Memory, Perception};
+use crate::{actions::attack::Hit, Faction, FactionRelations, Relation};
+
+/// Entries with less threat than this are dropped, unless the enemy is
+/// sensed. Enemies sensed for the first time start with this much.
+const MIN_THREAT: f32 = 0.01;
+/// A returning mob is home once this close to where it spawned.
+const HOME_DISTANCE: f32 = 1.0;
+
+/// How a kind of mob picks its target.
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Targeting {
+    /// Threat per second for a sensed enemy right next to the mob, falling
+    /// off to nothing at the edge of its sight range.
+    pub proximity: f32,
+    /// Threat per point of damage taken.
+    pub damage: f32,
+    /// The fraction of threat lost per second.
+    pub decay: f32,
+    /// How many times the current target's threat another enemy needs to
+    /// become the target instead.
+    pub switch_ratio: f32,
+    /// How far from its spawn the mob follows anyone.
+    pub leash: f32,
+}
+
+impl Default for Targeting {
+    fn default() -> Self {
+        Self {
+            proximity: 0.5,
+            damage: 2.0,
+            decay: 0.2,
+            switch_ratio: 1.3,
+            leash: 12.0,
+        }
+    }
+}
+
+#[derive(Component, Default, Clone, Debug)]
+pub struct Threat {
+    pub table: HashMap<Entity, f32>,
+    pub target: Option<Entity>,
+}
+
+/// Where a mob spawned, and whether it is on its way back there.
+#[derive(Component, Clone, Debug)]
+pub struct Leash {
+    pub home: Vec3,
+    pub returning: bool,
+}
+
+impl Leash {
+    pub fn new(home: Vec3) -> Self {
+        Self {
+            home,
+            returning: false,
+        }
+    }
+}
+
+/// `threat` after `delta` seconds of gaining `gain` per second while losing
+/// `decay` of it per second. The same however `delta` is split up.
+fn accumulate(threat: f32, gain: f32, decay: f32, delta: f32) -> f32 {
+    if decay <= 0.0 {
+        return threat + gain * delta;
+    }
+    let kept = (-decay * delta).exp();
+    threat * kept + gain / decay * (1.0 - kept)
+}
+
+impl Threat {
+    /// The entry with the most threat among those the mob knows the position
+    /// of.
+    fn highest(&self, known: &HashMap<Entity, Vec3>) -> Option<(Entity, f32)> {
+        self.table
+            .iter()
+            .filter(|(enemy, _)| known.contains_key(*enemy))
+            .map(|(enemy, threat)| (*enemy, *threat))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+    }
+
+    /// Update threat tables and leashes, and point each mob's `Memory` at
+    /// its target.
+    pub fn system(
+        mut mobs: Query<(
+            Entity,
+            &Transform,
+            &Faction,
+            &Perception,
+            &Targeting,
+            &mut Threat,
+            &mut Memory,
+            &mut Leash,
+        )>,
+        positions: Query<&Transform>,
+        factions: Query<&Faction>,
+        relations: Res<FactionRelations>,
+        mut hits: EventReader<Hit>,
+        time: Res<Time>,
+    ) {
+        let delta = time.delta_seconds();
+
+        let mut damage_taken: HashMap<Entity, Vec<(Entity, f32)>> = HashMap::new();
+        for hit in &mut hits {
+            if let Some(attacker) = hit.attacker.filter(|_| hit.damage > 0.0) {
+                damage_taken
+                    .entry(hit.target)
+                    .or_default()
+                    .push((attacker, hit.damage));
+            }
+        }
+
+        for (
+            entity,
+            transform,
+            faction,
+            perception,
+            targeting,
+            mut threat,
+            mut memory,
+            mut leash,
+        ) in &mut mobs
+        {
+            let relation = |other: Entity| {
+                factions
+                    .get(other)
+                    .map_or(Relation::Neutral, |other| relations.get(*faction, *other))
+            };
+
+            let from_home = transform.translation.xz().distance(leash.home.xz());
+            if leash.returning && from_home < HOME_DISTANCE {
+                leash.returning = false;
+            } else if !leash.returning && from_home > targeting.leash {
+                leash.returning = true;
+                *threat = Threat::default();
+                memory.forget();
+            }
+            if leash.returning {
+                continue;
+            }
+
+            // The target was forgotten, so it can't be chased any more.
+            if memory.target.is_none() {
+                if let Some(target) = threat.target.take() {
+                    threat.table.remove(&target);
+                }
+            }
+
+            let mut known: HashMap<Entity, Vec3> = memory.contacts.iter().copied().collect();
+
+            let sight_range = perception.sight_range.max(f32::EPSILON);
+            let mut gains: HashMap<Entity, f32> = HashMap::new();
+            for (enemy, position) in &memory.contacts {
+                if relation(*enemy) != Relation::Hostile {
+                    continue;
+                }
+                let closeness = 1.0 - position.distance(transform.translation) / sight_range;
+                gains.insert(*enemy, targeting.proximity * closeness.max(0.0));
+                threat.table.entry(*enemy).or_insert(MIN_THREAT);
+            }
+
+            for (enemy, value) in &mut threat.table {
+                let gain = gains.get(enemy).copied().unwrap_or(0.0);
+                *value = accumulate(*value, gain, targeting.decay, delta);
+            }
+
+            // Being hurt gives away where the attacker is.
+            for (attacker, damage) in damage_taken.get(&entity).into_iter().flatten() {
+                let Ok(attacker_transform) = positions.get(*attacker) else {
+                    continue;
+                };
+                if *attacker == entity || relation(*attacker) == Relation::Friendly {
+                    continue;
+                }
+                known.insert(*attacker, attacker_transform.translation);
+                *threat.table.entry(*attacker).or_default() += targeting.damage * damage;
+            }
+
+            threat.table.retain(|enemy, value| {
+                (*value >= MIN_THREAT || gains.contains_key(enemy)) && positions.contains(*enemy)
+            });
+
+            let current = threat
+                .target
+                .and_then(|target| threat.table.get(&target).copied());
+            if current.is_none() && threat.target.take().is_some() {
+                memory.forget();
+            }
+
+            let Some((best, best_threat)) = threat.highest(&known) else {
+                continue;
+            };
+            if threat.target == Some(best)
+                || current.is_some_and(|current| best_threat < current * targeting.switch_ratio)
+            {
+                continue;
+            }
+
+            threat.target = Some(best);
+            memory.remember(best, known[&best]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulate_is_independent_of_frame_rate() {
+        let once = accumulate(1.0, 0.5, 0.2, 1.0);
+        let mut sixty = 1.0;
+        for _ in 0..60 {
+            sixty = accumulate(sixty, 0.5, 0.2, 1.0 / 60.0);
+        }
+        let mut ten = 1.0;
+        for _ in 0..10 {
+            ten = accumulate(ten, 0.5, 0.2, 0.1);
+        }
+
+        assert!((once - sixty).abs() < 1e-4);
+        assert!((once - ten).abs() < 1e-4);
+    }
+
+    #[test]
+    fn accumulate_settles_where_gain_matches_decay() {
+        let settled = accumulate(0.0, 0.5, 0.2, 100.0);
+        assert!((settled - 2.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn accumulate_without_decay_only_gains() {
+        assert!((accumulate(1.0, 0.5, 0.0, 2.0) - 2.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn a_sensed_enemy_becomes_the_target() {
+        let mut world = World::new();
+        world.init_resource::<FactionRelations>();
+        world.init_resource::<Events<Hit>>();
+        world.init_resource::<Time>();
+
+        let enemy = world
+            .spawn((Transform::from_xyz(9.0, 0.0, 0.0), Faction::PLAYER))
+            .id();
+        let mob = world
+            .spawn((
+                Transform::default(),
+                Faction::MOBS,
+                Perception::default(),
+                Targeting::default(),
+                Threat::default(),
+                Memory {
+                    contacts: vec![(enemy, Vec3::new(9.0, 0.0, 0.0))],
+                    ..default()
+                },
+                Leash::new(Vec3::ZERO),
+            ))
+            .id();
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(Threat::system);
+        schedule.run(&mut world);
+
+        assert_eq!(world.get::<Threat>(mob).unwrap().target, Some(enemy));
+        assert_eq!(world.get::<Memory>(mob).unwrap().target, Some(enemy));
+    }
+}
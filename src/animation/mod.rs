@@ -7,6 +7,7 @@ pub struct Animations {
     pub run: Handle<AnimationClip>,
     pub idle: Handle<AnimationClip>,
     pub attack: Handle<AnimationClip>,
+    pub charge: Handle<AnimationClip>,
 }
 
 #[derive(Component)]
@@ -0,0 +1,100 @@
+//! Board coordinates.
+//!
+//! Cell `(i, j)` is centred on `x = i`, `z = j`, so a world position belongs
+//! to the cell it rounds to. Every character gets a `GridPosition`, kept up to
+//! date each frame, and a `CellEntered` event whenever it changes.
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::Cell;
+use crate::{ControlledPlayer, Game};
+
+/// The cell a character is in. Kept at the last cell on the board while
+/// off it.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GridPosition(pub (usize, usize));
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CellEntered {
+    pub entity: Entity,
+    pub to: (usize, usize),
+}
+
+impl Game {
+    /// Board size as `(i, j)`.
+    pub fn board_size(&self) -> (usize, usize) {
+        (self.board.first().map_or(0, Vec::len), self.board.len())
+    }
+
+    #[must_use]
+    pub fn cell(&self, (i, j): (usize, usize)) -> Option<&Cell> {
+        self.board.get(j)?.get(i)
+    }
+
+    /// The cell a world position falls in, if it is on the board.
+    #[must_use]
+    pub fn world_to_cell(&self, position: Vec3) -> Option<(usize, usize)> {
+        let i = usize::try_from(position.x.round() as i32).ok()?;
+        let j = usize::try_from(position.z.round() as i32).ok()?;
+        self.cell((i, j)).map(|_| (i, j))
+    }
+
+    /// The middle of the top of a cell, where things stand on it.
+    #[must_use]
+    pub fn cell_to_world(&self, cell: (usize, usize)) -> Option<Vec3> {
+        let (i, j) = cell;
+        let cell = self.cell(cell)?;
+        let top = cell.column().map_or(cell.height, |column| column.top);
+        Some(Vec3::new(i as f32, top, j as f32))
+    }
+
+    /// The cells next to a cell, not counting diagonals.
+    pub fn neighbours(&self, (i, j): (usize, usize)) -> impl Iterator<Item = (usize, usize)> + '_ {
+        [(1, 0), (-1, 0), (0, 1), (0, -1)]
+            .into_iter()
+            .filter_map(move |(di, dj)| {
+                let cell = (i.checked_add_signed(di)?, j.checked_add_signed(dj)?);
+                self.cell(cell).map(|_| cell)
+            })
+    }
+}
+
+impl GridPosition {
+    /// Track the cell of every character, keeping `Game.player` in sync
+    /// with the player's.
+    pub fn system(
+        mut commands: Commands,
+        mut game: ResMut<Game>,
+        mut query: Query<
+            (
+                Entity,
+                &Transform,
+                Option<&mut GridPosition>,
+                Option<&ControlledPlayer>,
+            ),
+            With<KinematicCharacterController>,
+        >,
+        mut entered: EventWriter<CellEntered>,
+    ) {
+        for (entity, transform, position, player) in &mut query {
+            let Some(cell) = game.world_to_cell(transform.translation) else {
+                continue;
+            };
+
+            match position {
+                Some(position) if position.0 == cell => continue,
+                Some(mut position) => position.0 = cell,
+                None => {
+                    commands.entity(entity).insert(GridPosition(cell));
+                }
+            }
+
+            if player.is_some() {
+                (game.player.i, game.player.j) = cell;
+            }
+
+            entered.send(CellEntered { entity, to: cell });
+        }
+    }
+}
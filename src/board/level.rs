@@ -0,0 +1,319 @@
+//! Hand-authored levels.
+//!
+//! A level is a RON file ending in `.level.ron`, loaded through the asset
+//! server. The board is written as one string per row, one character per cell:
+//!
+//! | char | cell  |
+//! |------|-------|
+//! | `.`  | floor |
+//! | `#`  | wall  |
+//! | `~`  | water |
+//! | `^`  | lava  |
+//! | `o`  | pit   |
+//! | `=`  | ice   |
+//!
+//! A mob's `kind` names its archetype in `assets/mobs`, such as `"enemy"`,
+//! which closes in to fight, or `"ranged"`, which keeps its distance and
+//! shoots. A level with a mob of an unknown kind is rejected.
+//!
+//! Heights are optional. If given, there is one per cell in the same layout,
+//! and a level with rows of heights missing or of the wrong width is
+//! rejected. Walls rise `WALL_HEIGHT` above their height. Cells are addressed
+//! as `(i, j)`, where `j` is the row and `i` the column. The player and mobs
+//! can't spawn on a wall or pit, and neither can spawn points, where the
+//! encounter director brings in its waves. Props can be marked on the minimap
+//! with an `icon`, `Pickup` or `Exit`.
+//!
+//! ```ron
+//! (
+//!     size: (3, 2),
+//!     rows: [
+//!         "..#",
+//!         ".o.",
+//!     ],
+//!     heights: [
+//!         [0.0, 0.0, 0.5],
+//!         [0.0, 0.0, 0.1],
+//!     ],
+//!     player: (0, 0),
+//!     mobs: [(kind: "enemy", cell: (2, 1))],
+//!     spawn_points: [(1, 0)],
+//!     props: [
+//!         (scene: "models/world/tile.glb#Scene0", cell: (0, 1), icon: Some(Exit)),
+//!     ],
+//! )
+//! ```
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::BoxedFuture;
+use serde::Deserialize;
+
+use super::{Cell, CellKind, MobSpawn, Prop};
+
+#[derive(Debug, Deserialize, TypeUuid, TypePath)]
+#[uuid = "6c2a3d4e-96f1-4b0e-8c55-0a1f7e2b9d31"]
+pub struct Level {
+    /// Board size as `(i, j)`.
+    pub size: (usize, usize),
+    pub rows: Vec<String>,
+    /// Per-cell heights, `[j][i]`. Flat when left out.
+    #[serde(default)]
+    pub heights: Vec<Vec<f32>>,
+    pub player: (usize, usize),
+    #[serde(default)]
+    pub mobs: Vec<MobSpawn>,
+    /// Where waves of mobs come from. The mob spawns when left out.
+    #[serde(default)]
+    pub spawn_points: Vec<(usize, usize)>,
+    #[serde(default)]
+    pub props: Vec<Prop>,
+}
+
+impl Level {
+    /// Build the board, or explain what is wrong with the level.
+    pub fn board(&self) -> Result<Vec<Vec<Cell>>, String> {
+        let (size_i, size_j) = self.size;
+
+        if self.rows.len() != size_j {
+            return Err(format!("expected {size_j} rows, found {}", self.rows.len()));
+        }
+        if !self.heights.is_empty() {
+            if self.heights.len() != size_j {
+                return Err(format!(
+                    "expected {size_j} rows of heights, found {}",
+                    self.heights.len()
+                ));
+            }
+            if let Some(j) = self.heights.iter().position(|row| row.len() != size_i) {
+                return Err(format!("row {j} of heights should be {size_i} cells wide"));
+            }
+        }
+
+        let in_bounds = |(i, j): (usize, usize)| i < size_i && j < size_j;
+        if !in_bounds(self.player) {
+            return Err(format!("player spawn {:?} is off the board", self.player));
+        }
+        if let Some(mob) = self.mobs.iter().find(|mob| !in_bounds(mob.cell)) {
+            return Err(format!("mob spawn {:?} is off the board", mob.cell));
+        }
+        if let Some(point) = self.spawn_points.iter().find(|point| !in_bounds(**point)) {
+            return Err(format!("spawn point {point:?} is off the board"));
+        }
+
+        let board: Vec<Vec<Cell>> = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(j, row)| {
+                if row.chars().count() != size_i {
+                    return Err(format!("row {j} should be {size_i} cells wide"));
+                }
+
+                row.chars()
+                    .enumerate()
+                    .map(|(i, symbol)| {
+                        let kind = CellKind::from_symbol(symbol)
+                            .ok_or_else(|| format!("unknown cell {symbol:?} at ({i}, {j})"))?;
+                        let height = self
+                            .heights
+                            .get(j)
+                            .and_then(|row| row.get(i))
+                            .copied()
+                            .unwrap_or_default();
+                        Ok(Cell { height, kind })
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+
+        let walkable = |(i, j): (usize, usize)| board[j][i].kind.is_walkable();
+        if !walkable(self.player) {
+            return Err(format!("player spawn {:?} is a wall or pit", self.player));
+        }
+        if let Some(mob) = self.mobs.iter().find(|mob| !walkable(mob.cell)) {
+            return Err(format!("mob spawn {:?} is a wall or pit", mob.cell));
+        }
+        if let Some(point) = self.spawn_points.iter().find(|point| !walkable(**point)) {
+            return Err(format!("spawn point {point:?} is a wall or pit"));
+        }
+
+        Ok(board)
+    }
+
+    /// Check that every mob spawn names an archetype `known` knows of.
+    pub fn check_mobs(&self, known: impl Fn(&str) -> bool) -> Result<(), String> {
+        match self.mobs.iter().find(|mob| !known(&mob.kind)) {
+            Some(mob) => Err(format!(
+                "mob spawn {:?} is of unknown archetype {:?}",
+                mob.cell, mob.kind
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct LevelLoader;
+
+impl AssetLoader for LevelLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let level: Level = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(level));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["level.ron"]
+    }
+}
+
+/// The level the current board is loaded from, if any.
+#[derive(Resource)]
+pub struct LevelHandle(pub Handle<Level>);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hud::MinimapIcon;
+
+    /// The example from the module docs, which the other tests break in one
+    /// way each.
+    const EXAMPLE: &str = r#"(
+        size: (3, 2),
+        rows: [
+            "..#",
+            ".o.",
+        ],
+        heights: [
+            [0.0, 0.0, 0.5],
+            [0.0, 0.0, 0.1],
+        ],
+        player: (0, 0),
+        mobs: [(kind: "enemy", cell: (2, 1))],
+        spawn_points: [(1, 0)],
+        props: [
+            (scene: "models/world/tile.glb#Scene0", cell: (0, 1), icon: Some(Exit)),
+        ],
+    )"#;
+
+    fn example() -> Level {
+        ron::from_str(EXAMPLE).unwrap()
+    }
+
+    #[test]
+    fn builds_the_board_from_rows_and_heights() {
+        let board = example().board().unwrap();
+
+        assert_eq!(board.len(), 2);
+        assert_eq!(board[0][2].kind, CellKind::Wall);
+        assert_eq!(board[1][1].kind, CellKind::Pit);
+        assert!((board[0][2].height - 0.5).abs() < f32::EPSILON);
+        assert!((board[1][2].height - 0.1).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn props_can_have_a_minimap_icon() {
+        let level = example();
+        assert!(matches!(level.props[0].icon, Some(MinimapIcon::Exit)));
+    }
+
+    #[test]
+    fn leaving_heights_out_is_flat() {
+        let mut level = example();
+        level.heights.clear();
+
+        let board = level.board().unwrap();
+        assert!(board
+            .iter()
+            .flatten()
+            .all(|cell| cell.height.abs() < f32::EPSILON));
+    }
+
+    #[test]
+    fn the_bundled_levels_are_valid() {
+        let level: Level =
+            ron::from_str(include_str!("../../assets/levels/arena.level.ron")).unwrap();
+        assert!(level.board().is_ok());
+    }
+
+    #[test]
+    fn rejects_a_wrong_number_of_rows() {
+        let mut level = example();
+        level.rows.pop();
+        assert!(level.board().is_err());
+    }
+
+    #[test]
+    fn rejects_rows_of_the_wrong_width() {
+        let mut level = example();
+        level.rows[1] = "..".to_string();
+        assert!(level.board().is_err());
+    }
+
+    #[test]
+    fn rejects_a_wrong_number_of_height_rows() {
+        let mut level = example();
+        level.heights.pop();
+        assert!(level.board().is_err());
+
+        let mut level = example();
+        level.heights.push(vec![0.0; 3]);
+        assert!(level.board().is_err());
+    }
+
+    #[test]
+    fn rejects_height_rows_of_the_wrong_width() {
+        let mut level = example();
+        level.heights[1].pop();
+        assert!(level.board().is_err());
+
+        let mut level = example();
+        level.heights[0].push(0.0);
+        assert!(level.board().is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_cells() {
+        let mut level = example();
+        level.rows[0] = "..?".to_string();
+        assert!(level.board().is_err());
+    }
+
+    #[test]
+    fn rejects_spawns_off_the_board() {
+        let mut level = example();
+        level.player = (3, 0);
+        assert!(level.board().is_err());
+
+        let mut level = example();
+        level.mobs[0].cell = (0, 2);
+        assert!(level.board().is_err());
+
+        let mut level = example();
+        level.spawn_points.push((5, 5));
+        assert!(level.board().is_err());
+    }
+
+    #[test]
+    fn rejects_spawns_on_walls_and_pits() {
+        let mut level = example();
+        level.player = (2, 0);
+        assert!(level.board().is_err());
+
+        let mut level = example();
+        level.mobs[0].cell = (1, 1);
+        assert!(level.board().is_err());
+
+        let mut level = example();
+        level.spawn_points.push((1, 1));
+        assert!(level.board().is_err());
+    }
+}
@@ -0,0 +1,222 @@
+//! Meshes and colliders for the whole board.
+//!
+//! Every cell is a solid column from `COLUMN_FLOOR` up to its top. Instead of
+//! an entity per cell, the board is drawn as one mesh per `CHUNK_SIZE` square
+//! of cells and surface, and collides as a single compound collider in which
+//! neighbouring columns of the same height are merged into one box. Merging
+//! also means there are no seams on flat ground for the character controller
+//! to catch on.
+//!
+//! Each column's top is drawn with one of a few surfaces, so e.g. water and
+//! lava can have materials of their own. Sides are always drawn with surface
+//! `0`.
+//!
+//! This only depends on bevy and rapier, so `benches/board.rs` can include it.
+
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+use bevy_rapier3d::prelude::*;
+
+/// Columns reach down to here, so raised cells have no gap beneath.
+pub const COLUMN_FLOOR: f32 = -0.5;
+
+/// How far under a translucent top the opaque one is.
+const TRANSLUCENT_DEPTH: f32 = 0.05;
+
+/// Cells per side of a chunk mesh.
+pub const CHUNK_SIZE: usize = 8;
+
+/// The solid part of a cell. Cells without one, like pits, are `None`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Column {
+    pub top: f32,
+    pub top_color: Color,
+    pub side_color: Color,
+    /// Which of the caller's materials the top is drawn with. A translucent
+    /// top has an opaque one in `side_color` under it, so the column doesn't
+    /// look hollow.
+    pub surface: usize,
+}
+
+/// Columns by `[j][i]`, with cell `(i, j)` centred on `x = i`, `z = j`.
+pub type Columns = [Vec<Option<Column>>];
+
+/// One collider for the whole board, or `None` if there is nothing solid.
+pub fn board_collider(columns: &Columns) -> Option<Collider> {
+    let size_j = columns.len();
+    let size_i = columns.first().map_or(0, Vec::len);
+    let mut merged = vec![vec![false; size_i]; size_j];
+    let mut shapes = Vec::new();
+
+    let top_at = |i: usize, j: usize| columns[j][i].map(|column| column.top);
+
+    for j0 in 0..size_j {
+        for i0 in 0..size_i {
+            let Some(top) = top_at(i0, j0) else {
+                continue;
+            };
+            if merged[j0][i0] {
+                continue;
+            }
+
+            // Grow a rectangle of equal tops: first along the row, then down
+            // as long as the whole span matches.
+            let fits = |merged: &[Vec<bool>], i: usize, j: usize| {
+                !merged[j][i] && top_at(i, j) == Some(top)
+            };
+
+            let mut i1 = i0 + 1;
+            while i1 < size_i && fits(&merged, i1, j0) {
+                i1 += 1;
+            }
+
+            let mut j1 = j0 + 1;
+            while j1 < size_j && (i0..i1).all(|i| fits(&merged, i, j1)) {
+                j1 += 1;
+            }
+
+            for row in &mut merged[j0..j1] {
+                row[i0..i1].fill(true);
+            }
+
+            let width = (i1 - i0) as f32;
+            let depth = (j1 - j0) as f32;
+            let half_height = (top - COLUMN_FLOOR).max(0.0) / 2.0;
+            let center = Vec3::new(
+                i0 as f32 + width / 2.0 - 0.5,
+                COLUMN_FLOOR + half_height,
+                j0 as f32 + depth / 2.0 - 0.5,
+            );
+
+            shapes.push((
+                center,
+                Quat::IDENTITY,
+                Collider::cuboid(width / 2.0, half_height, depth / 2.0),
+            ));
+        }
+    }
+
+    (!shapes.is_empty()).then(|| Collider::compound(shapes))
+}
+
+#[derive(Default)]
+struct MeshBuilder {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuilder {
+    /// A quad from corners given counter-clockwise, seen from the front.
+    fn quad(&mut self, corners: [Vec3; 4], normal: Vec3, color: Color) {
+        let start = self.positions.len() as u32;
+        for corner in corners {
+            self.positions.push(corner.to_array());
+            self.normals.push(normal.to_array());
+            self.colors.push(color.as_linear_rgba_f32());
+        }
+        self.indices
+            .extend([start, start + 1, start + 2, start, start + 2, start + 3]);
+    }
+
+    fn build(self) -> Mesh {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+        mesh.set_indices(Some(Indices::U32(self.indices)));
+        mesh
+    }
+}
+
+/// The board as vertex-coloured meshes, one per chunk and surface, with the
+/// surface each is drawn with. Only the sides of a column that stick out
+/// above its neighbours are drawn.
+pub fn chunk_meshes(columns: &Columns) -> Vec<(usize, Mesh)> {
+    let size_j = columns.len();
+    let size_i = columns.first().map_or(0, Vec::len);
+    let mut meshes = Vec::new();
+
+    for chunk_j in (0..size_j).step_by(CHUNK_SIZE) {
+        for chunk_i in (0..size_i).step_by(CHUNK_SIZE) {
+            let mut builders: Vec<MeshBuilder> = Vec::new();
+
+            for j in chunk_j..(chunk_j + CHUNK_SIZE).min(size_j) {
+                for i in chunk_i..(chunk_i + CHUNK_SIZE).min(size_i) {
+                    add_column(&mut builders, columns, i, j);
+                }
+            }
+
+            meshes.extend(
+                builders
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, builder)| !builder.indices.is_empty())
+                    .map(|(surface, builder)| (surface, builder.build())),
+            );
+        }
+    }
+
+    meshes
+}
+
+fn add_column(builders: &mut Vec<MeshBuilder>, columns: &Columns, i: usize, j: usize) {
+    let Some(column) = columns[j][i] else {
+        return;
+    };
+
+    if builders.len() <= column.surface {
+        builders.resize_with(column.surface + 1, MeshBuilder::default);
+    }
+
+    let (x, z) = (i as f32, j as f32);
+    let top = column.top;
+    let top_quad = |y: f32| {
+        [
+            Vec3::new(x - 0.5, y, z + 0.5),
+            Vec3::new(x + 0.5, y, z + 0.5),
+            Vec3::new(x + 0.5, y, z - 0.5),
+            Vec3::new(x - 0.5, y, z - 0.5),
+        ]
+    };
+
+    builders[column.surface].quad(top_quad(top), Vec3::Y, column.top_color);
+    if column.top_color.a() < 1.0 {
+        builders[0].quad(
+            top_quad(top - TRANSLUCENT_DEPTH),
+            Vec3::Y,
+            column.side_color,
+        );
+    }
+
+    for (di, dj) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+        let neighbour = i
+            .checked_add_signed(di)
+            .zip(j.checked_add_signed(dj))
+            .and_then(|(i, j)| *columns.get(j)?.get(i)?);
+        let bottom = neighbour
+            .map_or(COLUMN_FLOOR, |neighbour| neighbour.top)
+            .max(COLUMN_FLOOR);
+        if bottom >= top {
+            continue;
+        }
+
+        let normal = Vec3::new(di as f32, 0.0, dj as f32);
+        // Along the face, keeping the winding counter-clockwise from outside.
+        let along = Vec3::Y.cross(normal) * 0.5;
+        let center = Vec3::new(x, 0.0, z) + normal * 0.5;
+
+        builders[0].quad(
+            [
+                center - along + Vec3::Y * bottom,
+                center + along + Vec3::Y * bottom,
+                center + along + Vec3::Y * top,
+                center - along + Vec3::Y * top,
+            ],
+            normal,
+            column.side_color,
+        );
+    }
+}
@@ -0,0 +1,568 @@
+//! The board the game is played on.
+//!
+//! Boards are either loaded from a level file (see `level`), or generated
+//! from a seed, so the same seed always gives the same board. Heights come
+//! from `noise`, and plateaus (with a ramp up), pits and walls are placed on
+//! top of that. The area around the player's spawn in the middle is always
+//! left flat.
+//!
+//! What each kind of cell does to whoever stands on it is in `terrain`.
+
+use bevy::asset::LoadState;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use iyes_progress::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::{
+    agent::{MobArchetype, MobRegistry},
+    hud::MinimapIcon,
+    save::Save,
+    Game, GameState, PauseState,
+};
+
+pub mod grid;
+pub use grid::*;
+
+pub mod level;
+pub use level::*;
+
+pub mod mesh;
+pub use mesh::*;
+
+pub mod noise;
+pub use noise::*;
+
+pub mod terrain;
+pub use terrain::*;
+
+pub const BOARD_SIZE_I: usize = 14;
+pub const BOARD_SIZE_J: usize = 21;
+
+/// How many rows of the board `generate_board` fills in per frame.
+const BOARD_ROWS_PER_FRAME: usize = 4;
+
+pub const PLATEAU_HEIGHT: f32 = 0.8;
+/// Half way up a plateau, so each step is within the controller's autostep.
+pub const RAMP_HEIGHT: f32 = 0.4;
+/// Tiles are drawn and collide this far above their cell's height.
+pub const TILE_HALF_HEIGHT: f32 = 0.2;
+/// How far walls rise above their cell.
+pub const WALL_HEIGHT: f32 = 1.5;
+/// The kill plane: whatever falls below this has fallen off the board.
+pub const PIT_DEPTH: f32 = -3.0;
+
+/// How quickly movement catches up with input on most cells, and on ice.
+/// At 10.0 and up it is immediate at any normal frame rate.
+pub const GROUND_FRICTION: f32 = 10.0;
+pub const ICE_FRICTION: f32 = 0.3;
+
+/// The colours of the tile model the board used to be built from.
+const GRASS_COLOR: Color = Color::rgb(0.27, 0.71, 0.55);
+const DIRT_COLOR: Color = Color::rgb(0.98, 0.71, 0.45);
+
+/// Cells this close to the spawn are never given features.
+const SPAWN_CLEARANCE: f32 = 3.0;
+
+/// Kinds of cell whose tops get a material of their own. Every other kind,
+/// and every side, is drawn like the first.
+pub const SURFACES: [CellKind; 4] = [
+    CellKind::Floor,
+    CellKind::Water,
+    CellKind::Lava,
+    CellKind::Ice,
+];
+
+/// Where the generator puts the first mob, on boards big enough for it.
+const GENERATED_MOB_CELL: (usize, usize) = (5, 7);
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum CellKind {
+    #[default]
+    Floor,
+    Wall,
+    Water,
+    Lava,
+    Pit,
+    Ice,
+}
+
+impl CellKind {
+    pub fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '.' => Some(CellKind::Floor),
+            '#' => Some(CellKind::Wall),
+            '~' => Some(CellKind::Water),
+            '^' => Some(CellKind::Lava),
+            'o' => Some(CellKind::Pit),
+            '=' => Some(CellKind::Ice),
+            _ => None,
+        }
+    }
+
+    /// Whether a character can be spawned on this kind of cell.
+    pub fn is_walkable(self) -> bool {
+        !matches!(self, CellKind::Wall | CellKind::Pit)
+    }
+
+    /// Whether the player can be put back here after falling off the board.
+    pub fn is_safe(self) -> bool {
+        matches!(self, CellKind::Floor | CellKind::Water | CellKind::Ice)
+    }
+
+    pub fn friction(self) -> f32 {
+        match self {
+            CellKind::Ice => ICE_FRICTION,
+            _ => GROUND_FRICTION,
+        }
+    }
+
+    /// The colour of the cell's top, on the board and on the minimap. `None`
+    /// for plain floor.
+    pub fn color(self) -> Option<Color> {
+        match self {
+            CellKind::Floor => None,
+            CellKind::Wall => Some(Color::GRAY),
+            CellKind::Water => Some(Color::rgba(0.1, 0.35, 0.8, 0.7)),
+            CellKind::Lava => Some(Color::ORANGE_RED),
+            CellKind::Pit => Some(Color::BLACK),
+            CellKind::Ice => Some(Color::rgba(0.75, 0.9, 1.0, 0.8)),
+        }
+    }
+
+    /// Which of `SURFACES` the cell's top is drawn with.
+    pub fn surface(self) -> usize {
+        SURFACES.iter().position(|kind| *kind == self).unwrap_or(0)
+    }
+
+    /// The material tops of this kind are drawn with. Their colour comes from
+    /// the board mesh.
+    pub fn material(self) -> StandardMaterial {
+        StandardMaterial {
+            base_color: Color::WHITE,
+            emissive: if self == CellKind::Lava {
+                self.color().unwrap_or(Color::BLACK)
+            } else {
+                Color::BLACK
+            },
+            alpha_mode: if self.color().is_some_and(|color| color.a() < 1.0) {
+                AlphaMode::Blend
+            } else {
+                AlphaMode::Opaque
+            },
+            perceptual_roughness: match self {
+                CellKind::Floor => 1.0,
+                CellKind::Ice => 0.1,
+                _ => 0.8,
+            },
+            ..default()
+        }
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub struct Cell {
+    pub height: f32,
+    pub kind: CellKind,
+}
+
+impl Cell {
+    /// The solid part of the cell, if it has one.
+    pub fn column(&self) -> Option<Column> {
+        let top = match self.kind {
+            CellKind::Pit => return None,
+            CellKind::Wall => self.height + WALL_HEIGHT + TILE_HALF_HEIGHT,
+            _ => self.height + TILE_HALF_HEIGHT,
+        };
+
+        Some(Column {
+            top,
+            top_color: self.kind.color().unwrap_or(GRASS_COLOR),
+            side_color: if self.kind == CellKind::Wall {
+                Color::GRAY
+            } else {
+                DIRT_COLOR
+            },
+            surface: self.kind.surface(),
+        })
+    }
+}
+
+/// The columns of a whole board, for `board_collider` and `chunk_meshes`.
+pub fn board_columns(board: &[Vec<Cell>]) -> Vec<Vec<Option<Column>>> {
+    board
+        .iter()
+        .map(|row| row.iter().map(Cell::column).collect())
+        .collect()
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct MobSpawn {
+    /// The name of the mob's archetype.
+    pub kind: String,
+    pub cell: (usize, usize),
+}
+
+/// A piece of scenery placed on a cell.
+#[derive(Deserialize, Clone, Debug)]
+pub struct Prop {
+    /// Asset path of the scene, e.g. `"models/world/tile.glb#Scene0"`.
+    pub scene: String,
+    pub cell: (usize, usize),
+    /// Rotation around the vertical axis, in degrees.
+    #[serde(default)]
+    pub rotation: f32,
+    /// What to mark the prop with on the minimap, e.g. `Some(Exit)`.
+    #[serde(default)]
+    pub icon: Option<MinimapIcon>,
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct BoardConfig {
+    /// Load this level instead of generating a board, e.g.
+    /// `"levels/arena.level.ron"`. Can also be set with the `LEVEL`
+    /// environment variable.
+    pub level: Option<String>,
+    /// Use this seed for every run. A new one is rolled per run when `None`.
+    /// Can also be set with the `BOARD_SEED` environment variable.
+    pub seed: Option<u64>,
+    /// Board size as `(i, j)`.
+    pub size: (usize, usize),
+    /// How far the noise moves cells up or down.
+    pub height_amplitude: f32,
+    pub plateaus: usize,
+    pub pits: usize,
+    pub walls: usize,
+}
+
+impl Default for BoardConfig {
+    fn default() -> Self {
+        Self {
+            level: std::env::var("LEVEL").ok(),
+            seed: std::env::var("BOARD_SEED")
+                .ok()
+                .and_then(|seed| seed.parse().ok()),
+            size: (BOARD_SIZE_I, BOARD_SIZE_J),
+            height_amplitude: 0.15,
+            plateaus: 2,
+            pits: 3,
+            walls: 6,
+        }
+    }
+}
+
+/// The seed the current board was generated from.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct BoardSeed(pub u64);
+
+#[derive(Default)]
+pub struct BoardPlugin;
+
+impl Plugin for BoardPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BoardConfig>()
+            .add_event::<CellEntered>()
+            .add_asset::<Level>()
+            .init_asset_loader::<LevelLoader>()
+            .add_systems(
+                OnEnter(GameState::LoadingGame),
+                (roll_seed, clear_board, load_level),
+            )
+            .add_systems(
+                Update,
+                generate_board
+                    .track_progress()
+                    .run_if(in_state(GameState::LoadingGame)),
+            )
+            .add_systems(
+                Update,
+                (
+                    GridPosition::system.before(Ground::system),
+                    Ground::system,
+                    BurnTick::system.after(Ground::system),
+                    track_safe_cell,
+                    fall_system,
+                )
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PauseState::Running)),
+            );
+    }
+}
+
+/// Pick the seed for this run: a continued save's, the configured one, or a new one.
+pub fn roll_seed(mut commands: Commands, config: Res<BoardConfig>, save: Option<Res<Save>>) {
+    let seed = save
+        .map(|save| save.seed)
+        .or(config.seed)
+        .unwrap_or_else(|| rand::thread_rng().gen());
+
+    info!("Board seed: {seed}");
+    commands.insert_resource(BoardSeed(seed));
+}
+
+pub fn clear_board(mut game: ResMut<Game>) {
+    game.board.clear();
+    game.mob_spawns.clear();
+    game.spawn_points.clear();
+    game.props.clear();
+}
+
+pub fn load_level(
+    mut commands: Commands,
+    config: Res<BoardConfig>,
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<AssetsLoading>,
+) {
+    let Some(path) = &config.level else {
+        commands.remove_resource::<LevelHandle>();
+        return;
+    };
+
+    let level: Handle<Level> = asset_server.load(path.as_str());
+    loading.add(&level);
+    commands.insert_resource(LevelHandle(level));
+}
+
+/// What loading a level takes besides its handle.
+#[derive(SystemParam)]
+pub struct LevelAssets<'w> {
+    levels: Res<'w, Assets<Level>>,
+    asset_server: Res<'w, AssetServer>,
+    registry: Res<'w, MobRegistry>,
+    archetypes: Res<'w, Assets<MobArchetype>>,
+}
+
+/// Build the board from the level once it and the mob archetypes have
+/// loaded. Falls back to a generated board if the level is missing or broken,
+/// or names a mob archetype that doesn't exist.
+fn board_from_level(
+    commands: &mut Commands,
+    game: &mut Game,
+    handle: &LevelHandle,
+    assets: &LevelAssets,
+) -> Progress {
+    let LevelAssets {
+        levels,
+        asset_server,
+        registry,
+        archetypes,
+    } = assets;
+
+    if !game.board.is_empty() {
+        return true.into();
+    }
+
+    if asset_server.get_load_state(&handle.0) == LoadState::Failed {
+        error!("Could not load level, generating a board instead");
+        commands.remove_resource::<LevelHandle>();
+        return false.into();
+    }
+
+    let Some(level) = levels.get(&handle.0) else {
+        return false.into();
+    };
+    if !registry.is_ready(asset_server) {
+        return false.into();
+    }
+
+    let board = level.board().and_then(|board| {
+        level.check_mobs(|kind| registry.contains(kind, archetypes))?;
+        Ok(board)
+    });
+    match board {
+        Ok(board) => {
+            game.board = board;
+            game.player_spawn = level.player;
+            game.mob_spawns.clone_from(&level.mobs);
+            game.spawn_points.clone_from(&level.spawn_points);
+            game.props.clone_from(&level.props);
+            true.into()
+        }
+        Err(error) => {
+            error!("Invalid level, generating a board instead: {error}");
+            commands.remove_resource::<LevelHandle>();
+            false.into()
+        }
+    }
+}
+
+/// Fill in the board a few rows at a time, reporting to the loading screen.
+/// Features are placed once the last row is in.
+pub fn generate_board(
+    mut commands: Commands,
+    mut game: ResMut<Game>,
+    config: Res<BoardConfig>,
+    seed: Res<BoardSeed>,
+    level: Option<Res<LevelHandle>>,
+    assets: LevelAssets,
+) -> Progress {
+    if let Some(level) = level {
+        return board_from_level(&mut commands, &mut game, &level, &assets);
+    }
+
+    let (size_i, size_j) = config.size;
+    let start = game.board.len();
+    let end = (start + BOARD_ROWS_PER_FRAME).min(size_j);
+
+    for j in start..end {
+        game.board.push(generate_row(&config, seed.0, j));
+    }
+
+    if start < size_j && end == size_j {
+        place_features(&mut game.board, &config, seed.0);
+        game.player_spawn = (size_i / 2, size_j / 2);
+        let mob_cell = generated_mob_cell(config.size);
+        game.mob_spawns = vec![MobSpawn {
+            kind: "enemy".to_string(),
+            cell: mob_cell,
+        }];
+        game.spawn_points = vec![mob_cell];
+    }
+
+    Progress {
+        done: game.board.len() as u32,
+        total: size_j as u32,
+    }
+}
+
+/// Row `j` of a board, before features are placed.
+fn generate_row(config: &BoardConfig, seed: u64, j: usize) -> Vec<Cell> {
+    (0..config.size.0)
+        .map(|i| Cell {
+            height: terrain_height(seed, config.height_amplitude, i, j),
+            kind: CellKind::Floor,
+        })
+        .collect()
+}
+
+/// `GENERATED_MOB_CELL`, moved onto smaller boards.
+fn generated_mob_cell(size: (usize, usize)) -> (usize, usize) {
+    (
+        GENERATED_MOB_CELL.0.min(size.0.saturating_sub(1)),
+        GENERATED_MOB_CELL.1.min(size.1.saturating_sub(1)),
+    )
+}
+
+/// Whether the cell is close to the player's spawn or is the mob's.
+fn near_spawn(size: (usize, usize), i: usize, j: usize) -> bool {
+    let spawn = Vec2::new((size.0 / 2) as f32, (size.1 / 2) as f32);
+    Vec2::new(i as f32, j as f32).distance(spawn) < SPAWN_CLEARANCE
+        || (i, j) == generated_mob_cell(size)
+}
+
+/// A random rectangle of `width` by `depth` cells that fits on the board.
+fn random_area(
+    rng: &mut StdRng,
+    size: (usize, usize),
+    width: usize,
+    depth: usize,
+) -> Option<(usize, usize)> {
+    if width >= size.0 || depth >= size.1 {
+        return None;
+    }
+    Some((
+        rng.gen_range(0..size.0 - width),
+        rng.gen_range(0..size.1 - depth),
+    ))
+}
+
+fn place_features(board: &mut [Vec<Cell>], config: &BoardConfig, seed: u64) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let size = config.size;
+
+    let set_height = |board: &mut [Vec<Cell>], i: usize, j: usize, height: f32| {
+        if !near_spawn(size, i, j) {
+            board[j][i].height = height;
+        }
+    };
+    let set_kind = |board: &mut [Vec<Cell>], i: usize, j: usize, kind: CellKind| {
+        if !near_spawn(size, i, j) {
+            board[j][i].kind = kind;
+        }
+    };
+
+    for _ in 0..config.plateaus {
+        let (width, depth) = (rng.gen_range(3..6), rng.gen_range(3..6));
+        let Some((i0, j0)) = random_area(&mut rng, size, width, depth) else {
+            continue;
+        };
+
+        for j in j0..j0 + depth {
+            for i in i0..i0 + width {
+                set_height(board, i, j, PLATEAU_HEIGHT);
+            }
+        }
+
+        // A ramp along one side, if there is room for it.
+        let ramp_j = if j0 > 0 { j0 - 1 } else { j0 + depth };
+        if ramp_j < size.1 {
+            for i in i0..i0 + width {
+                set_height(board, i, ramp_j, RAMP_HEIGHT);
+            }
+        }
+    }
+
+    for _ in 0..config.pits {
+        let (width, depth) = (rng.gen_range(1..3), rng.gen_range(1..3));
+        let Some((i0, j0)) = random_area(&mut rng, size, width, depth) else {
+            continue;
+        };
+
+        for j in j0..j0 + depth {
+            for i in i0..i0 + width {
+                set_kind(board, i, j, CellKind::Pit);
+            }
+        }
+    }
+
+    for _ in 0..config.walls {
+        let Some((i, j)) = random_area(&mut rng, size, 1, 1) else {
+            continue;
+        };
+        set_kind(board, i, j, CellKind::Wall);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A whole board, as `generate_board` builds it over several frames.
+    fn generate(config: &BoardConfig, seed: u64) -> Vec<Vec<Cell>> {
+        let mut board: Vec<_> = (0..config.size.1)
+            .map(|j| generate_row(config, seed, j))
+            .collect();
+        place_features(&mut board, config, seed);
+        board
+    }
+
+    #[test]
+    fn the_same_seed_gives_the_same_board() {
+        let config = BoardConfig::default();
+        assert_eq!(generate(&config, 42), generate(&config, 42));
+    }
+
+    #[test]
+    fn another_seed_gives_another_board() {
+        let config = BoardConfig::default();
+        assert_ne!(generate(&config, 1), generate(&config, 2));
+    }
+
+    #[test]
+    fn features_stay_clear_of_the_spawn() {
+        let config = BoardConfig {
+            plateaus: 20,
+            ..default()
+        };
+        let board = generate(&config, 7);
+
+        for (j, row) in board.iter().enumerate() {
+            for (i, cell) in row.iter().enumerate() {
+                if near_spawn(config.size, i, j) {
+                    assert!(cell.height.abs() <= config.height_amplitude);
+                }
+            }
+        }
+    }
+}
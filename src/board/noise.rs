@@ -0,0 +1,95 @@
+//! Terrain heights for generated boards.
+//!
+//! Heights come from a few octaves of value noise, so the same seed always
+//! gives the same terrain. They are rounded to steps of `HEIGHT_STEP`, so
+//! neighbouring cells often end up level with each other and their columns
+//! merge in `board_collider`.
+//!
+//! This doesn't depend on anything, so `benches/board.rs` can include it.
+
+/// Noise lattice spacing and weight, from coarse to fine.
+const OCTAVES: [(usize, f32); 3] = [(8, 1.0), (4, 0.5), (2, 0.25)];
+
+/// Generated heights are multiples of this.
+pub const HEIGHT_STEP: f32 = 0.05;
+
+/// A pseudo-random value in `0.0..1.0` for a lattice point.
+fn lattice(seed: u64, x: usize, y: usize) -> f32 {
+    let mut hash = seed
+        ^ (x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+
+    // splitmix64 finalizer
+    hash ^= hash >> 30;
+    hash = hash.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    hash ^= hash >> 27;
+    hash = hash.wrapping_mul(0x94D0_49BB_1331_11EB);
+    hash ^= hash >> 31;
+
+    (hash >> 40) as f32 / (1u64 << 24) as f32
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Value noise at cell `(i, j)` on a lattice with the given spacing.
+fn value_noise(seed: u64, spacing: usize, i: usize, j: usize) -> f32 {
+    let (x, y) = (i / spacing, j / spacing);
+    let tx = smoothstep((i % spacing) as f32 / spacing as f32);
+    let ty = smoothstep((j % spacing) as f32 / spacing as f32);
+
+    let top = lattice(seed, x, y) * (1.0 - tx) + lattice(seed, x + 1, y) * tx;
+    let bottom = lattice(seed, x, y + 1) * (1.0 - tx) + lattice(seed, x + 1, y + 1) * tx;
+    top * (1.0 - ty) + bottom * ty
+}
+
+/// Octaves of value noise, normalized to `0.0..1.0`.
+pub fn fractal_noise(seed: u64, i: usize, j: usize) -> f32 {
+    let mut total = 0.0;
+    let mut weights = 0.0;
+
+    for (octave, (spacing, weight)) in OCTAVES.iter().enumerate() {
+        total += weight * value_noise(seed.wrapping_add(octave as u64), *spacing, i, j);
+        weights += weight;
+    }
+
+    total / weights
+}
+
+/// The height of cell `(i, j)`, within `amplitude` of zero.
+pub fn terrain_height(seed: u64, amplitude: f32, i: usize, j: usize) -> f32 {
+    let height = amplitude * (2.0 * fractal_noise(seed, i, j) - 1.0);
+    (height / HEIGHT_STEP).round() * HEIGHT_STEP
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn heights(seed: u64, amplitude: f32) -> impl Iterator<Item = f32> {
+        (0..32).flat_map(move |j| (0..32).map(move |i| terrain_height(seed, amplitude, i, j)))
+    }
+
+    #[test]
+    fn noise_stays_in_range() {
+        for j in 0..32 {
+            for i in 0..32 {
+                assert!((0.0..1.0).contains(&fractal_noise(3, i, j)));
+            }
+        }
+    }
+
+    #[test]
+    fn heights_stay_within_the_amplitude() {
+        assert!(heights(3, 0.15).all(|height| height.abs() <= 0.15 + f32::EPSILON));
+    }
+
+    #[test]
+    fn heights_are_whole_steps() {
+        assert!(heights(3, 0.4).all(|height| {
+            let steps = height / HEIGHT_STEP;
+            (steps - steps.round()).abs() < 1e-3
+        }));
+    }
+}
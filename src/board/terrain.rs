@@ -0,0 +1,206 @@
+//! What the cell under a character does to it.
+//!
+//! Walls and pits are handled by the colliders `setup_scene` gives them (or
+//! doesn't), so this is water slowing, lava burning, ice sliding and falling
+//! off the board. The player is put back on the last safe cell they stood on
+//! after a fall, mobs are gone for good.
+
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{CellKind, PIT_DEPTH};
+use crate::{
+    actions::{
+        attack::{Hit, HitKind, Knockback},
+        conditions::{Burning, Condition, Slowed},
+        Momentum,
+    },
+    agent::Health,
+    ControlledPlayer, Game,
+};
+
+/// How long water keeps slowing after stepping out of it.
+const SLOW_DURATION: Duration = Duration::from_millis(300);
+/// How long lava keeps burning after stepping out of it.
+const BURN_DURATION: Duration = Duration::from_secs(2);
+const BURN_INTERVAL: Duration = Duration::from_secs(1);
+const BURN_DAMAGE: f32 = 1.0;
+/// Damage for falling off the board.
+const FALL_DAMAGE: f32 = 2.0;
+
+/// The kind of cell a character is over. Anything off the board is a pit.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Ground(pub CellKind);
+
+/// Deals `BURN_DAMAGE` every `BURN_INTERVAL` while `Burning`.
+#[derive(Component)]
+pub struct BurnTick(Timer);
+
+fn kind_at(game: &Game, position: Vec3) -> CellKind {
+    game.world_to_cell(position)
+        .and_then(|cell| game.cell(cell))
+        .map_or(CellKind::Pit, |cell| cell.kind)
+}
+
+impl Ground {
+    /// Track the cell under every character and apply its condition to those
+    /// standing on it.
+    pub fn system(
+        mut commands: Commands,
+        game: Res<Game>,
+        mut query: Query<
+            (
+                Entity,
+                &Transform,
+                Option<&mut Ground>,
+                Option<&KinematicCharacterControllerOutput>,
+                Option<&mut Condition<Slowed>>,
+                Option<&mut Condition<Burning>>,
+            ),
+            With<KinematicCharacterController>,
+        >,
+    ) {
+        for (entity, transform, ground, output, slowed, burning) in &mut query {
+            let kind = kind_at(&game, transform.translation);
+
+            match ground {
+                Some(mut ground) if ground.0 != kind => ground.0 = kind,
+                Some(_) => {}
+                None => {
+                    commands.entity(entity).insert(Ground(kind));
+                }
+            }
+
+            if !output.is_some_and(|output| output.grounded) {
+                continue;
+            }
+
+            // Standing in it keeps the condition going, rather than letting
+            // it run out and starting it over.
+            match (kind, slowed, burning) {
+                (CellKind::Water, Some(mut slowed), _) => slowed.refresh(SLOW_DURATION),
+                (CellKind::Water, None, _) => {
+                    commands
+                        .entity(entity)
+                        .insert(Condition::<Slowed>::new(SLOW_DURATION));
+                }
+                (CellKind::Lava, _, Some(mut burning)) => burning.refresh(BURN_DURATION),
+                (CellKind::Lava, _, None) => {
+                    commands.entity(entity).insert((
+                        Condition::<Burning>::new(BURN_DURATION),
+                        BurnTick(Timer::new(BURN_INTERVAL, TimerMode::Repeating)),
+                    ));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl BurnTick {
+    pub fn system(
+        mut commands: Commands,
+        mut query: Query<(
+            Entity,
+            &mut BurnTick,
+            &mut Health,
+            &Transform,
+            Option<&Condition<Burning>>,
+        )>,
+        mut hits: EventWriter<Hit>,
+        time: Res<Time>,
+    ) {
+        for (entity, mut tick, mut health, transform, burning) in &mut query {
+            if burning.is_none() {
+                commands.entity(entity).remove::<BurnTick>();
+                continue;
+            }
+
+            if tick.0.tick(time.delta()).just_finished() {
+                health.current -= BURN_DAMAGE;
+                hits.send(Hit {
+                    target: entity,
+                    attacker: None,
+                    damage: BURN_DAMAGE,
+                    position: transform.translation,
+                    kind: HitKind::DamageOverTime,
+                });
+            }
+        }
+    }
+}
+
+/// Keep `Game.player.safe` on the last cell the player stood on safely.
+pub fn track_safe_cell(
+    mut game: ResMut<Game>,
+    players: Query<(&Transform, &KinematicCharacterControllerOutput), With<ControlledPlayer>>,
+) {
+    let Ok((transform, output)) = players.get_single() else {
+        return;
+    };
+    if !output.grounded {
+        return;
+    }
+
+    let Some(cell) = game.world_to_cell(transform.translation) else {
+        return;
+    };
+    if game.board[cell.1][cell.0].kind.is_safe() && game.player.safe != cell {
+        game.player.safe = cell;
+    }
+}
+
+/// Whatever falls below `PIT_DEPTH` has fallen off the board. The player
+/// takes `FALL_DAMAGE` and, if that wasn't the end of them, is put back on
+/// their last safe cell. Mobs are despawned.
+pub fn fall_system(
+    mut commands: Commands,
+    game: Res<Game>,
+    mut query: Query<(
+        Entity,
+        &mut Transform,
+        &mut Health,
+        Option<&mut KinematicCharacterController>,
+        Option<&mut Momentum>,
+        Option<&ControlledPlayer>,
+    )>,
+    mut hits: EventWriter<Hit>,
+) {
+    for (entity, mut transform, mut health, controller, momentum, player) in &mut query {
+        if transform.translation.y >= PIT_DEPTH || health.current <= 0.0 {
+            continue;
+        }
+
+        if player.is_none() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        health.current -= FALL_DAMAGE;
+        if health.current <= 0.0 {
+            continue;
+        }
+
+        if let Some(safe) = game.cell_to_world(game.player.safe) {
+            transform.translation = safe + Vec3::Y;
+        }
+
+        if let Some(mut controller) = controller {
+            controller.translation = None;
+        }
+        if let Some(mut momentum) = momentum {
+            momentum.0 = Vec3::ZERO;
+        }
+        commands.entity(entity).remove::<Knockback>();
+
+        hits.send(Hit {
+            target: entity,
+            attacker: None,
+            damage: FALL_DAMAGE,
+            position: transform.translation,
+            kind: HitKind::Normal,
+        });
+    }
+}
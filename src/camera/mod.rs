@@ -1,5 +1,21 @@
 use crate::Game;
 use bevy::prelude::*;
+use std::time::Duration;
+
+/// Points the camera at something other than the player for a while, e.g. a
+/// boss changing phase.
+#[derive(Resource, Default)]
+pub struct CameraFocus {
+    pub target: Option<Entity>,
+    pub timer: Timer,
+}
+
+impl CameraFocus {
+    pub fn focus(&mut self, target: Entity, duration: Duration) {
+        self.target = Some(target);
+        self.timer = Timer::new(duration, TimerMode::Once);
+    }
+}
 
 pub fn setup_cameras(mut commands: Commands, mut game: ResMut<Game>) {
     game.camera_should_focus = Vec3::ZERO;
@@ -18,11 +34,27 @@ pub fn setup_cameras(mut commands: Commands, mut game: ResMut<Game>) {
 pub fn focus_system(
     time: Res<Time>,
     mut game: ResMut<Game>,
+    mut focus: ResMut<CameraFocus>,
     mut transforms: ParamSet<(Query<&mut Transform, With<Camera3d>>, Query<&Transform>)>,
 ) {
     const SPEED: f32 = 5.0;
 
-    if let Some(player_entity) = game.player.entity {
+    focus.timer.tick(time.delta());
+    let focused = focus
+        .target
+        .filter(|_| !focus.timer.finished())
+        .and_then(|target| {
+            transforms
+                .p1()
+                .get(target)
+                .ok()
+                .map(|transform| transform.translation)
+        });
+
+    if let Some(target) = focused {
+        game.camera_should_focus = target;
+    } else if let Some(player_entity) = game.player.entity {
+        focus.target = None;
         if let Ok(player_transform) = transforms.p1().get(player_entity) {
             game.camera_should_focus = player_transform.translation;
         }
@@ -0,0 +1,712 @@
+//! Waves of mobs, and winning the run.
+//!
+//! The encounter director reads an `Encounter` from a `.encounter.ron` file
+//! and brings its waves in one after another. Each wave is made of groups of
+//! mobs of one archetype, each group showing up `delay` seconds into the wave.
+//! Every mob is telegraphed by a pulsing marker on its spawn point for
+//! `telegraph` seconds before it appears. Once every mob hostile to the player
+//! is dead the player gets a break, and clearing the last wave wins the run.
+//!
+//! ```ron
+//! (
+//!     break_duration: 5.0,
+//!     telegraph: 1.5,
+//!     waves: [
+//!         (groups: [(archetype: "enemy", count: 2)]),
+//!         (
+//!             groups: [
+//!                 (archetype: "enemy", count: 3),
+//!                 (archetype: "ranged", count: 1, delay: 4.0),
+//!             ],
+//!             delay: 8.0,
+//!         ),
+//!     ],
+//! )
+//! ```
+//!
+//! Mobs come in at the level's `spawn_points`, or at its mob spawns if it has
+//! none, preferring points away from the player. Mobs beyond one per point
+//! are spread onto the cells around it.
+//!
+//! An encounter naming an archetype that isn't loaded is not played at all.
+
+use bevy::asset::{AssetLoader, LoadContext, LoadedAsset};
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use bevy::reflect::{TypePath, TypeUuid};
+use bevy::utils::BoxedFuture;
+use iyes_progress::prelude::AssetsLoading;
+use serde::Deserialize;
+
+use crate::{
+    agent::{Health, MobArchetype, MobRegistry, SpawnMob},
+    ControlledPlayer, Faction, FactionRelations, Game, GameState, PauseState,
+};
+
+/// Spawn points closer than this to the player are only used when there are
+/// no others.
+const MIN_PLAYER_DISTANCE: f32 = 4.0;
+const TELEGRAPH_RADIUS: f32 = 0.4;
+/// Pulses per second of a telegraph marker.
+const TELEGRAPH_PULSE: f32 = 3.0;
+
+/// Which encounter to play.
+#[derive(Resource, Clone, Debug)]
+pub struct DirectorConfig {
+    /// Path of the encounter, relative to `assets`. Can also be set with the
+    /// `ENCOUNTER` environment variable.
+    pub encounter: String,
+}
+
+impl Default for DirectorConfig {
+    fn default() -> Self {
+        Self {
+            encounter: std::env::var("ENCOUNTER")
+                .unwrap_or_else(|_| "encounters/default.encounter.ron".to_string()),
+        }
+    }
+}
+
+/// Some mobs of one archetype within a wave.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WaveGroup {
+    pub archetype: String,
+    pub count: usize,
+    /// Seconds into the wave before the group is telegraphed.
+    #[serde(default)]
+    pub delay: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Wave {
+    pub groups: Vec<WaveGroup>,
+    /// Seconds of rest before this wave, instead of the encounter's
+    /// `break_duration`.
+    #[serde(default)]
+    pub delay: Option<f32>,
+}
+
+fn default_break() -> f32 {
+    5.0
+}
+
+fn default_telegraph() -> f32 {
+    1.5
+}
+
+#[derive(Debug, Deserialize, TypeUuid, TypePath)]
+#[uuid = "9b4e7f21-3c6d-4a8e-b152-7d0c9e3f6a48"]
+pub struct Encounter {
+    pub waves: Vec<Wave>,
+    /// Seconds of rest before each wave, the first one included.
+    #[serde(default = "default_break")]
+    pub break_duration: f32,
+    /// Seconds a spawn is telegraphed before the mob appears.
+    #[serde(default = "default_telegraph")]
+    pub telegraph: f32,
+}
+
+impl Encounter {
+    /// Parse an encounter, with a wave's `delay` given as a plain number.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ron::error::SpannedError> {
+        ron::Options::default()
+            .with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME)
+            .from_bytes(bytes)
+    }
+}
+
+#[derive(Default)]
+pub struct EncounterLoader;
+
+impl AssetLoader for EncounterLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let encounter = Encounter::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(encounter));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["encounter.ron"]
+    }
+}
+
+/// The encounter being played.
+#[derive(Resource)]
+pub struct EncounterHandle(pub Handle<Encounter>);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DirectorPhase {
+    /// Waiting for the encounter to load.
+    #[default]
+    Starting,
+    /// Resting before the next wave.
+    Break,
+    Fighting,
+    /// Every wave has been cleared, or there were none.
+    Done,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct Director {
+    pub phase: DirectorPhase,
+    /// How many waves have started.
+    pub wave: usize,
+    /// How many waves the encounter has.
+    pub total: usize,
+    /// Mobs hostile to the player that are alive or about to spawn.
+    pub alive: usize,
+    /// Counts down the break.
+    pub timer: Timer,
+    /// Groups of the current wave still to come, with the seconds left
+    /// before each does.
+    pending: Vec<(f32, WaveGroup)>,
+    /// Groups whose time has come, to be telegraphed.
+    due: Vec<WaveGroup>,
+}
+
+/// A mob about to spawn, shown as a marker on the ground.
+#[derive(Component)]
+pub struct SpawnTelegraph {
+    pub archetype: String,
+    pub position: Vec3,
+    pub timer: Timer,
+}
+
+#[derive(Resource)]
+pub struct TelegraphAssets {
+    pub mesh: Handle<Mesh>,
+    pub material: Handle<StandardMaterial>,
+}
+
+impl FromWorld for TelegraphAssets {
+    fn from_world(world: &mut World) -> Self {
+        let mesh = world.resource_mut::<Assets<Mesh>>().add(
+            shape::Cylinder {
+                radius: TELEGRAPH_RADIUS,
+                height: 0.02,
+                ..default()
+            }
+            .into(),
+        );
+        let material = world
+            .resource_mut::<Assets<StandardMaterial>>()
+            .add(StandardMaterial {
+                base_color: Color::rgba(0.9, 0.1, 0.1, 0.6),
+                emissive: Color::rgb(0.6, 0.05, 0.05),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            });
+        Self { mesh, material }
+    }
+}
+
+#[derive(Component)]
+pub struct VictoryBanner;
+
+#[derive(Default)]
+pub struct DirectorPlugin;
+
+impl Plugin for DirectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DirectorConfig>()
+            .init_resource::<Director>()
+            .init_resource::<TelegraphAssets>()
+            .add_asset::<Encounter>()
+            .init_asset_loader::<EncounterLoader>()
+            .add_systems(OnEnter(GameState::LoadingGame), load_encounter)
+            .add_systems(OnEnter(GameState::Playing), Director::reset_system)
+            .add_systems(OnEnter(GameState::Victory), spawn_victory_banner)
+            .add_systems(
+                Update,
+                (
+                    (
+                        Director::count_system,
+                        Director::system,
+                        Director::spawn_system,
+                    )
+                        .chain(),
+                    SpawnTelegraph::system.before(SpawnMob::system),
+                )
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PauseState::Running)),
+            );
+    }
+}
+
+pub fn load_encounter(
+    mut commands: Commands,
+    config: Res<DirectorConfig>,
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<AssetsLoading>,
+) {
+    let encounter: Handle<Encounter> = asset_server.load(config.encounter.as_str());
+    loading.add(&encounter);
+    commands.insert_resource(EncounterHandle(encounter));
+}
+
+/// Where to bring in `count` mobs: spawn points away from the player first,
+/// then the walkable cells around them.
+fn spawn_positions(game: &Game, player: Option<Vec3>, count: usize) -> Vec<Vec3> {
+    let mut points: Vec<(usize, usize)> = if game.spawn_points.is_empty() {
+        game.mob_spawns.iter().map(|spawn| spawn.cell).collect()
+    } else {
+        game.spawn_points.clone()
+    };
+
+    if let Some(player) = player {
+        let distance = |cell: (usize, usize)| {
+            game.cell_to_world(cell)
+                .map_or(0.0, |position| position.xz().distance(player.xz()))
+        };
+        points.sort_by(|a, b| distance(*b).total_cmp(&distance(*a)));
+        let far = points
+            .iter()
+            .filter(|cell| distance(**cell) >= MIN_PLAYER_DISTANCE)
+            .count();
+        if far > 0 {
+            points.truncate(far);
+        }
+    }
+
+    if points.is_empty() {
+        return Vec::new();
+    }
+
+    (0..count)
+        .filter_map(|n| {
+            let point = points[n % points.len()];
+            let round = n / points.len();
+            let cell = if round == 0 {
+                point
+            } else {
+                let around: Vec<_> = game
+                    .neighbours(point)
+                    .filter(|cell| game.cell(*cell).is_some_and(|cell| cell.kind.is_walkable()))
+                    .collect();
+                if around.is_empty() {
+                    point
+                } else {
+                    around[(round - 1) % around.len()]
+                }
+            };
+            game.cell_to_world(cell)
+        })
+        .map(|position| position + Vec3::Y)
+        .collect()
+}
+
+impl Director {
+    fn start_break(&mut self, encounter: &Encounter) {
+        let delay = encounter.waves[self.wave]
+            .delay
+            .unwrap_or(encounter.break_duration);
+        self.timer = Timer::from_seconds(delay.max(0.0), TimerMode::Once);
+        self.phase = DirectorPhase::Break;
+    }
+
+    fn start_wave(&mut self, encounter: &Encounter) {
+        self.pending = encounter.waves[self.wave]
+            .groups
+            .iter()
+            .map(|group| (group.delay, group.clone()))
+            .collect();
+        self.wave += 1;
+        self.phase = DirectorPhase::Fighting;
+        info!("Wave {} of {}", self.wave, self.total);
+    }
+
+    pub fn reset_system(mut director: ResMut<Director>) {
+        *director = Director::default();
+    }
+
+    /// Count down the groups still to come, moving those whose time has come
+    /// to `due`.
+    fn tick_pending(&mut self, elapsed: f32) {
+        let due = &mut self.due;
+        self.pending.retain_mut(|(delay, group)| {
+            *delay -= elapsed;
+            if *delay > 0.0 {
+                return true;
+            }
+            due.push(group.clone());
+            false
+        });
+    }
+
+    /// Whether every group of the wave has come and every mob is dead.
+    fn wave_cleared(&self) -> bool {
+        self.pending.is_empty() && self.due.is_empty() && self.alive == 0
+    }
+
+    /// Count the mobs hostile to the player, spawned or telegraphed.
+    pub fn count_system(
+        mut director: ResMut<Director>,
+        relations: Res<FactionRelations>,
+        mobs: Query<&Faction, (With<Health>, Without<ControlledPlayer>)>,
+        telegraphs: Query<(), With<SpawnTelegraph>>,
+    ) {
+        let alive = mobs
+            .iter()
+            .filter(|faction| relations.is_hostile(**faction, Faction::PLAYER))
+            .count()
+            + telegraphs.iter().count();
+        if director.alive != alive {
+            director.alive = alive;
+        }
+    }
+
+    /// Move through breaks and waves, and win the run after the last one.
+    pub fn system(
+        mut director: ResMut<Director>,
+        handle: Option<Res<EncounterHandle>>,
+        encounters: Res<Assets<Encounter>>,
+        registry: Res<MobRegistry>,
+        archetypes: Res<Assets<MobArchetype>>,
+        mut next_state: ResMut<NextState<GameState>>,
+        time: Res<Time>,
+    ) {
+        let Some(encounter) = handle.and_then(|handle| encounters.get(&handle.0)) else {
+            return;
+        };
+
+        match director.phase {
+            DirectorPhase::Starting => {
+                director.total = encounter.waves.len();
+                let unknown = encounter
+                    .waves
+                    .iter()
+                    .flat_map(|wave| &wave.groups)
+                    .find(|group| !registry.contains(&group.archetype, &archetypes));
+                if let Some(group) = unknown {
+                    // Without its mobs the encounter could be won without a
+                    // fight.
+                    error!("Unknown mob archetype {:?} in encounter", group.archetype);
+                    director.phase = DirectorPhase::Done;
+                } else if director.total == 0 {
+                    director.phase = DirectorPhase::Done;
+                } else {
+                    director.start_break(encounter);
+                }
+            }
+            DirectorPhase::Break => {
+                director.timer.tick(time.delta());
+                if director.timer.finished() {
+                    director.start_wave(encounter);
+                }
+            }
+            DirectorPhase::Fighting => {
+                if !director.pending.is_empty() {
+                    director.tick_pending(time.delta_seconds());
+                }
+
+                if director.wave_cleared() {
+                    if director.wave >= director.total {
+                        director.phase = DirectorPhase::Done;
+                        next_state.set(GameState::Victory);
+                    } else {
+                        director.start_break(encounter);
+                    }
+                }
+            }
+            DirectorPhase::Done => {}
+        }
+    }
+
+    /// Telegraph the mobs of every group that is due.
+    pub fn spawn_system(
+        mut commands: Commands,
+        mut director: ResMut<Director>,
+        handle: Option<Res<EncounterHandle>>,
+        encounters: Res<Assets<Encounter>>,
+        game: Res<Game>,
+        assets: Res<TelegraphAssets>,
+        players: Query<&Transform, With<ControlledPlayer>>,
+    ) {
+        if director.due.is_empty() {
+            return;
+        }
+        let Some(encounter) = handle.and_then(|handle| encounters.get(&handle.0)) else {
+            return;
+        };
+
+        let player = players
+            .get_single()
+            .ok()
+            .map(|transform| transform.translation);
+
+        for group in std::mem::take(&mut director.due) {
+            let positions = spawn_positions(&game, player, group.count);
+            if positions.is_empty() {
+                warn!("Nowhere to spawn wave mobs");
+            }
+            for position in positions {
+                commands.spawn(SpawnTelegraph::bundle(
+                    &group.archetype,
+                    position,
+                    encounter.telegraph,
+                    &assets,
+                ));
+                director.alive += 1;
+            }
+        }
+    }
+}
+
+impl SpawnTelegraph {
+    pub fn bundle(
+        archetype: &str,
+        position: Vec3,
+        duration: f32,
+        assets: &TelegraphAssets,
+    ) -> (SpawnTelegraph, PbrBundle) {
+        (
+            SpawnTelegraph {
+                archetype: archetype.to_string(),
+                position,
+                timer: Timer::from_seconds(duration.max(0.0), TimerMode::Once),
+            },
+            PbrBundle {
+                mesh: assets.mesh.clone(),
+                material: assets.material.clone(),
+                // Just above the ground under where the mob will stand.
+                transform: Transform::from_translation(position - Vec3::Y * 0.98),
+                ..default()
+            },
+        )
+    }
+
+    /// Pulse the markers, and bring in their mobs once they run out.
+    pub fn system(
+        mut commands: Commands,
+        mut telegraphs: Query<(Entity, &mut SpawnTelegraph, &mut Transform)>,
+        mut spawns: EventWriter<SpawnMob>,
+        time: Res<Time>,
+    ) {
+        for (entity, mut telegraph, mut transform) in &mut telegraphs {
+            telegraph.timer.tick(time.delta());
+
+            if telegraph.timer.finished() {
+                spawns.send(SpawnMob {
+                    archetype: telegraph.archetype.clone(),
+                    position: telegraph.position,
+                });
+                commands.entity(entity).despawn();
+                continue;
+            }
+
+            let pulse = (telegraph.timer.elapsed_secs() * TELEGRAPH_PULSE * std::f32::consts::TAU)
+                .sin()
+                * 0.5
+                + 0.5;
+            let grow = 0.5 + 0.5 * telegraph.timer.percent();
+            transform.scale = Vec3::new(grow + pulse * 0.2, 1.0, grow + pulse * 0.2);
+        }
+    }
+}
+
+fn spawn_victory_banner(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+            ..default()
+        })
+        .insert(VictoryBanner)
+        .with_children(|banner| {
+            banner.spawn(TextBundle::from_section(
+                "Victory!",
+                TextStyle {
+                    font_size: 64.0,
+                    color: Color::GOLD,
+                    ..default()
+                },
+            ));
+            banner.spawn(TextBundle::from_section(
+                "Every wave cleared. Press Space to play again.",
+                TextStyle {
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Cell, CellKind, MobSpawn};
+
+    /// A flat board from rows of level symbols, the first row at `j = 0`.
+    fn game(rows: &[&str], spawn_points: &[(usize, usize)]) -> Game {
+        Game {
+            board: rows
+                .iter()
+                .map(|row| {
+                    row.chars()
+                        .map(|symbol| Cell {
+                            height: 0.0,
+                            kind: CellKind::from_symbol(symbol).unwrap(),
+                        })
+                        .collect()
+                })
+                .collect(),
+            spawn_points: spawn_points.to_vec(),
+            ..default()
+        }
+    }
+
+    fn cells(game: &Game, positions: &[Vec3]) -> Vec<(usize, usize)> {
+        positions
+            .iter()
+            .map(|position| game.world_to_cell(*position).unwrap())
+            .collect()
+    }
+
+    fn encounter() -> Encounter {
+        Encounter::from_bytes(include_bytes!(
+            "../../assets/encounters/default.encounter.ron"
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn prefers_spawn_points_away_from_the_player() {
+        let game = game(&["........."], &[(1, 0), (8, 0)]);
+        let positions = spawn_positions(&game, Some(Vec3::ZERO), 1);
+        assert_eq!(cells(&game, &positions), [(8, 0)]);
+    }
+
+    #[test]
+    fn skips_spawn_points_next_to_the_player() {
+        let game = game(&["........."], &[(1, 0), (8, 0)]);
+        let positions = spawn_positions(&game, Some(Vec3::ZERO), 2);
+        assert_eq!(cells(&game, &positions), [(8, 0), (7, 0)]);
+    }
+
+    #[test]
+    fn uses_spawn_points_next_to_the_player_when_there_are_no_others() {
+        let game = game(&["........."], &[(1, 0)]);
+        let positions = spawn_positions(&game, Some(Vec3::ZERO), 1);
+        assert_eq!(cells(&game, &positions), [(1, 0)]);
+    }
+
+    #[test]
+    fn spreads_extra_mobs_onto_walkable_cells_around_a_point() {
+        let game = game(
+            &[
+                "#.#", //
+                "#.#", //
+                "###",
+            ],
+            &[(1, 1)],
+        );
+        let positions = spawn_positions(&game, None, 3);
+        assert_eq!(cells(&game, &positions), [(1, 1), (1, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn falls_back_to_the_mob_spawns() {
+        let mut game = game(&["....."], &[]);
+        game.mob_spawns = vec![MobSpawn {
+            kind: "enemy".to_string(),
+            cell: (3, 0),
+        }];
+        let positions = spawn_positions(&game, None, 1);
+        assert_eq!(cells(&game, &positions), [(3, 0)]);
+
+        game.mob_spawns.clear();
+        assert!(spawn_positions(&game, None, 1).is_empty());
+    }
+
+    #[test]
+    fn breaks_last_as_long_as_the_wave_asks() {
+        let mut encounter = encounter();
+        encounter.break_duration = 2.0;
+        encounter.waves[0].delay = None;
+        encounter.waves[1].delay = Some(8.0);
+        let mut director = Director::default();
+
+        director.start_break(&encounter);
+        assert_eq!(director.phase, DirectorPhase::Break);
+        assert!((director.timer.duration().as_secs_f32() - 2.0).abs() < f32::EPSILON);
+
+        director.wave = 1;
+        director.start_break(&encounter);
+        assert!((director.timer.duration().as_secs_f32() - 8.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn groups_come_when_their_delay_runs_out() {
+        let encounter = encounter();
+        let mut director = Director {
+            wave: 2,
+            ..default()
+        };
+        director.start_wave(&encounter);
+
+        director.tick_pending(1.0);
+        let due: Vec<f32> = director.due.iter().map(|group| group.delay).collect();
+        assert_eq!(due, [0.0]);
+        assert_eq!(director.pending.len(), 2);
+
+        director.tick_pending(1.5);
+        assert_eq!(director.due.len(), 2);
+        assert_eq!(director.pending.len(), 1);
+    }
+
+    #[test]
+    fn a_wave_is_cleared_once_every_group_has_come_and_died() {
+        let encounter = encounter();
+        let mut director = Director::default();
+        director.start_wave(&encounter);
+        assert!(!director.wave_cleared());
+
+        director.tick_pending(0.0);
+        assert!(director.pending.is_empty());
+        assert!(!director.wave_cleared());
+
+        director.due.clear();
+        director.alive = 2;
+        assert!(!director.wave_cleared());
+
+        director.alive = 0;
+        assert!(director.wave_cleared());
+    }
+
+    #[test]
+    fn waves_queue_their_groups_with_their_delays() {
+        let encounter = encounter();
+        let mut director = Director::default();
+
+        director.start_wave(&encounter);
+        assert_eq!(director.phase, DirectorPhase::Fighting);
+        assert_eq!(director.wave, 1);
+        assert_eq!(director.pending.len(), encounter.waves[0].groups.len());
+
+        director.start_wave(&encounter);
+        assert_eq!(director.wave, 2);
+        let delays: Vec<f32> = director.pending.iter().map(|(delay, _)| *delay).collect();
+        let expected: Vec<f32> = encounter.waves[1]
+            .groups
+            .iter()
+            .map(|group| group.delay)
+            .collect();
+        assert_eq!(delays, expected);
+    }
+}
@@ -0,0 +1,135 @@
+//! Who fights whom.
+//!
+//! Every character belongs to a `Faction`, and `FactionRelations` says how
+//! each pair of factions gets along. Mobs only go after hostile characters on
+//! their own, but fight back against neutral ones that hurt them. Attacks
+//! never land on friendly characters unless `friendly_fire` is on.
+
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Default, Debug, Deserialize)]
+#[serde(transparent)]
+pub struct Faction(pub u32);
+
+impl Faction {
+    pub const PLAYER: Self = Self(0);
+    pub const MOBS: Self = Self(1);
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Relation {
+    Hostile,
+    Neutral,
+    Friendly,
+}
+
+#[derive(Resource, Clone, Debug)]
+pub struct FactionRelations {
+    /// Keyed by the pair of faction ids, lowest first.
+    relations: HashMap<(u32, u32), Relation>,
+    /// How factions without an entry get along.
+    pub default: Relation,
+    /// Whether attacks hurt friendly characters.
+    pub friendly_fire: bool,
+}
+
+impl Default for FactionRelations {
+    fn default() -> Self {
+        let mut relations = Self {
+            relations: HashMap::new(),
+            default: Relation::Hostile,
+            friendly_fire: false,
+        };
+        relations.set(Faction::PLAYER, Faction::MOBS, Relation::Hostile);
+        relations
+    }
+}
+
+fn key(Faction(a): Faction, Faction(b): Faction) -> (u32, u32) {
+    (a.min(b), a.max(b))
+}
+
+impl FactionRelations {
+    /// How `a` and `b` get along, either way round. A faction is always
+    /// friendly with itself.
+    pub fn get(&self, a: Faction, b: Faction) -> Relation {
+        if a == b {
+            return Relation::Friendly;
+        }
+        self.relations
+            .get(&key(a, b))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    pub fn set(&mut self, a: Faction, b: Faction, relation: Relation) {
+        self.relations.insert(key(a, b), relation);
+    }
+
+    pub fn is_hostile(&self, a: Faction, b: Faction) -> bool {
+        self.get(a, b) == Relation::Hostile
+    }
+
+    /// Whether an attack by `attacker` lands on `target`.
+    pub fn can_hurt(&self, attacker: Faction, target: Faction) -> bool {
+        self.friendly_fire || self.get(attacker, target) != Relation::Friendly
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BANDITS: Faction = Faction(2);
+
+    #[test]
+    fn players_and_mobs_are_hostile() {
+        let relations = FactionRelations::default();
+        assert!(relations.is_hostile(Faction::PLAYER, Faction::MOBS));
+        assert!(relations.is_hostile(Faction::MOBS, Faction::PLAYER));
+    }
+
+    #[test]
+    fn a_faction_is_friendly_with_itself() {
+        let relations = FactionRelations {
+            default: Relation::Hostile,
+            ..default()
+        };
+        assert_eq!(relations.get(BANDITS, BANDITS), Relation::Friendly);
+    }
+
+    #[test]
+    fn relations_go_both_ways() {
+        let mut relations = FactionRelations::default();
+        relations.set(BANDITS, Faction::PLAYER, Relation::Neutral);
+
+        assert_eq!(relations.get(BANDITS, Faction::PLAYER), Relation::Neutral);
+        assert_eq!(relations.get(Faction::PLAYER, BANDITS), Relation::Neutral);
+    }
+
+    #[test]
+    fn unlisted_pairs_get_the_default() {
+        let relations = FactionRelations {
+            default: Relation::Neutral,
+            ..default()
+        };
+
+        assert_eq!(relations.get(BANDITS, Faction::MOBS), Relation::Neutral);
+        assert!(relations.is_hostile(Faction::PLAYER, Faction::MOBS));
+    }
+
+    #[test]
+    fn friendly_fire_lets_allies_hurt_each_other() {
+        let mut relations = FactionRelations::default();
+        relations.set(BANDITS, Faction::MOBS, Relation::Friendly);
+        assert!(!relations.can_hurt(BANDITS, Faction::MOBS));
+        assert!(!relations.can_hurt(Faction::MOBS, Faction::MOBS));
+        assert!(relations.can_hurt(Faction::PLAYER, Faction::MOBS));
+
+        relations.friendly_fire = true;
+        assert!(relations.can_hurt(BANDITS, Faction::MOBS));
+        assert!(relations.can_hurt(Faction::MOBS, Faction::MOBS));
+    }
+}
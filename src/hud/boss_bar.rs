@@ -0,0 +1,173 @@
+//! A health bar across the top of the screen while a boss is around.
+//!
+//! Shows the title and health of the boss that last changed phase, or of the
+//! first boss spawned until one does. Each new phase is announced under the
+//! bar, fading out over `ANNOUNCE_DURATION`.
+
+use bevy::prelude::*;
+
+use super::text_style;
+use crate::{
+    agent::{Boss, BossPhaseChanged, Health},
+    GameState, PauseState,
+};
+
+/// Seconds a phase announcement stays up.
+const ANNOUNCE_DURATION: f32 = 3.0;
+const BAR_WIDTH: f32 = 480.0;
+
+/// The boss shown, if it is still around.
+#[derive(Component, Default)]
+pub struct BossBar(Option<Entity>);
+
+#[derive(Component)]
+pub struct BossBarFill;
+
+#[derive(Component)]
+pub struct BossBarTitle;
+
+#[derive(Component)]
+pub struct BossAnnouncement(pub Timer);
+
+#[derive(Default)]
+pub struct BossBarPlugin;
+
+impl Plugin for BossBarPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Playing), spawn_boss_bar)
+            .add_systems(
+                Update,
+                (boss_bar_system, boss_announcement_system)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PauseState::Running)),
+            );
+    }
+}
+
+fn spawn_boss_bar(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(16.0),
+                width: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .insert(BossBar::default())
+        .with_children(|bar| {
+            bar.spawn(TextBundle::from_section(
+                "",
+                TextStyle {
+                    color: Color::GOLD,
+                    ..text_style(20.0)
+                },
+            ))
+            .insert(BossBarTitle);
+
+            bar.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Px(BAR_WIDTH),
+                    height: Val::Px(14.0),
+                    ..default()
+                },
+                background_color: Color::rgb(0.15, 0.05, 0.1).into(),
+                ..default()
+            })
+            .with_children(|track| {
+                track
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.6, 0.1, 0.5).into(),
+                        ..default()
+                    })
+                    .insert(BossBarFill);
+            });
+
+            bar.spawn(TextBundle::from_section("", text_style(18.0)))
+                .insert(BossAnnouncement(Timer::default()));
+        });
+}
+
+/// Show the bar while there is a boss, filled to its health.
+pub fn boss_bar_system(
+    mut changes: EventReader<BossPhaseChanged>,
+    bosses: Query<(Entity, &Boss, &Health)>,
+    mut bars: Query<(&mut BossBar, &mut Visibility)>,
+    mut fills: Query<&mut Style, With<BossBarFill>>,
+    mut titles: Query<&mut Text, With<BossBarTitle>>,
+) {
+    let latest = changes.iter().last().map(|change| change.boss);
+
+    let mut shown = None;
+    for (mut bar, mut visibility) in &mut bars {
+        if let Some(boss) = latest {
+            bar.0 = Some(boss);
+        }
+        if bar.0.is_none_or(|boss| !bosses.contains(boss)) {
+            // Stick to one boss until it dies or another changes phase,
+            // rather than whichever the query yields first each frame.
+            bar.0 = bosses.iter().map(|(entity, ..)| entity).min();
+        }
+
+        let target = if bar.0.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != target {
+            *visibility = target;
+        }
+        shown = bar.0;
+    }
+
+    let Some((_, boss, health)) = shown.and_then(|boss| bosses.get(boss).ok()) else {
+        return;
+    };
+
+    let width = Val::Percent(health.ratio() * 100.0);
+    for mut style in &mut fills {
+        if style.width != width {
+            style.width = width;
+        }
+    }
+
+    for mut text in &mut titles {
+        if text.sections[0].value != boss.title {
+            text.sections[0].value.clone_from(&boss.title);
+        }
+    }
+}
+
+pub fn boss_announcement_system(
+    mut changes: EventReader<BossPhaseChanged>,
+    mut announcements: Query<(&mut Text, &mut BossAnnouncement)>,
+    time: Res<Time>,
+) {
+    let latest = changes.iter().last();
+
+    for (mut text, mut announcement) in &mut announcements {
+        if let Some(change) = latest {
+            text.sections[0].value = format!("Phase {}: {}", change.phase, change.name);
+            announcement.0 = Timer::from_seconds(ANNOUNCE_DURATION, TimerMode::Once);
+        }
+
+        if announcement.0.finished() {
+            continue;
+        }
+        announcement.0.tick(time.delta());
+        text.sections[0]
+            .style
+            .color
+            .set_a(announcement.0.percent_left());
+    }
+}
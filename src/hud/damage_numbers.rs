@@ -0,0 +1,144 @@
+//! Floating combat text.
+//!
+//! Every `Hit` spawns a number at the hit location that rises and fades out
+//! over its own `LifeSpan`. Damage-over-time ticks landing on the same target
+//! in quick succession are added to the previous number instead.
+
+use bevy::prelude::*;
+use std::time::Duration;
+
+use crate::{
+    actions::attack::{Hit, HitKind, LifeSpan},
+    GameState, PauseState,
+};
+
+const LIFE_SPAN: Duration = Duration::from_millis(900);
+/// Damage-over-time ticks this close together share a number.
+const AGGREGATE_WINDOW: Duration = Duration::from_millis(400);
+/// World units per second.
+const RISE_SPEED: f32 = 1.2;
+/// Spawn this far above the hit so numbers clear the target's head.
+const HEAD_HEIGHT: f32 = 1.5;
+
+#[derive(Component)]
+pub struct DamageNumber {
+    pub target: Entity,
+    pub kind: HitKind,
+    pub amount: f32,
+    pub world_position: Vec3,
+}
+
+impl DamageNumber {
+    fn label(&self) -> String {
+        match self.kind {
+            HitKind::Normal | HitKind::DamageOverTime => format!("{:.0}", self.amount),
+            HitKind::Critical => format!("{:.0}!", self.amount),
+            HitKind::Heal => format!("+{:.0}", -self.amount),
+        }
+    }
+
+    fn style(&self) -> TextStyle {
+        let (font_size, color) = match self.kind {
+            HitKind::Normal => (22.0, Color::WHITE),
+            HitKind::Critical => (32.0, Color::ORANGE),
+            HitKind::Heal => (22.0, Color::GREEN),
+            HitKind::DamageOverTime => (16.0, Color::VIOLET),
+        };
+
+        TextStyle {
+            font_size,
+            color,
+            ..default()
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct DamageNumbersPlugin;
+
+impl Plugin for DamageNumbersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                spawn_damage_numbers,
+                float_system.after(spawn_damage_numbers),
+            )
+                .run_if(in_state(GameState::Playing))
+                .run_if(in_state(PauseState::Running)),
+        );
+    }
+}
+
+pub fn spawn_damage_numbers(
+    mut commands: Commands,
+    mut hits: EventReader<Hit>,
+    mut numbers: Query<(&mut DamageNumber, &LifeSpan, &mut Text)>,
+) {
+    'hits: for hit in &mut hits {
+        if hit.kind == HitKind::DamageOverTime {
+            for (mut number, lifespan, mut text) in &mut numbers {
+                if number.target == hit.target
+                    && number.kind == HitKind::DamageOverTime
+                    && lifespan.timer.elapsed() < AGGREGATE_WINDOW
+                {
+                    number.amount += hit.damage;
+                    text.sections[0].value = number.label();
+                    continue 'hits;
+                }
+            }
+        }
+
+        let number = DamageNumber {
+            target: hit.target,
+            kind: hit.kind,
+            amount: hit.damage,
+            world_position: hit.position + Vec3::Y * HEAD_HEIGHT,
+        };
+
+        commands
+            .spawn(
+                TextBundle::from_section(number.label(), number.style()).with_style(Style {
+                    position_type: PositionType::Absolute,
+                    ..default()
+                }),
+            )
+            .insert(LifeSpan::new(LIFE_SPAN))
+            .insert(number);
+    }
+}
+
+/// Rise in the world, follow the camera, and fade out.
+pub fn float_system(
+    camera: Query<(&Camera, &GlobalTransform)>,
+    mut numbers: Query<(
+        &mut DamageNumber,
+        &LifeSpan,
+        &mut Style,
+        &mut Text,
+        &mut Visibility,
+    )>,
+    time: Res<Time>,
+) {
+    let Ok((camera, camera_transform)) = camera.get_single() else {
+        return;
+    };
+
+    for (mut number, lifespan, mut style, mut text, mut visibility) in &mut numbers {
+        number.world_position.y += RISE_SPEED * time.delta_seconds();
+
+        let Some(position) = camera.world_to_viewport(camera_transform, number.world_position)
+        else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        *visibility = Visibility::Inherited;
+        style.left = Val::Px(position.x);
+        style.top = Val::Px(position.y);
+        text.sections[0]
+            .style
+            .color
+            .set_a(1.0 - lifespan.timer.percent());
+    }
+}
@@ -0,0 +1,337 @@
+//! A minimap of the board in the corner of the screen.
+//!
+//! The board is drawn into a small image, one pixel per cell, shaded by cell
+//! height or tinted by its kind. Cells the player hasn't been near yet are
+//! covered by fog. Markers are UI nodes placed on top, in percent of the map
+//! so that toggling the enlarged map with M only has to resize the frame.
+
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::ImageSampler;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+
+use crate::{
+    board::CellEntered, ControlledPlayer, Faction, FactionRelations, Game, GameState, PauseState,
+    Relation,
+};
+
+const SMALL: f32 = 160.0;
+const LARGE: f32 = 480.0;
+const MARKER_SIZE: f32 = 6.0;
+/// How far around the player cells get revealed, in cells.
+const EXPLORE_RADIUS: usize = 4;
+const FOG: Color = Color::rgb(0.08, 0.08, 0.11);
+
+/// Put this on entities that should show up on the minimap without a
+/// `Faction`. Level props get it from their `icon`.
+#[derive(Component, Clone, Copy, Debug, Deserialize)]
+pub enum MinimapIcon {
+    Pickup,
+    Exit,
+}
+
+#[derive(Resource)]
+pub struct Minimap {
+    pub image: Handle<Image>,
+    pub enlarged: bool,
+}
+
+/// Which cells of the board the player has seen, indexed `[j][i]`.
+#[derive(Resource, Default)]
+pub struct Explored(pub Vec<Vec<bool>>);
+
+/// Marker UI node for each tracked entity.
+#[derive(Resource, Default)]
+pub struct MinimapMarkers(pub HashMap<Entity, Entity>);
+
+#[derive(Component)]
+pub struct MinimapRoot;
+
+#[derive(Component)]
+pub struct MinimapMarker;
+
+#[derive(Default)]
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Explored>()
+            .init_resource::<MinimapMarkers>()
+            .add_systems(OnEnter(GameState::Playing), spawn_minimap)
+            .add_systems(
+                Update,
+                (
+                    explore_system,
+                    draw_minimap_system
+                        .after(explore_system)
+                        .run_if(resource_changed::<Explored>()),
+                    minimap_marker_system.after(explore_system),
+                    toggle_minimap_system,
+                )
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PauseState::Running)),
+            );
+    }
+}
+
+fn spawn_minimap(
+    mut commands: Commands,
+    game: Res<Game>,
+    mut images: ResMut<Assets<Image>>,
+    mut markers: ResMut<MinimapMarkers>,
+) {
+    let (width, height) = game.board_size();
+
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: width.max(1) as u32,
+            height: height.max(1) as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &FOG.as_rgba_u8(),
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler_descriptor = ImageSampler::nearest();
+    let image = images.add(image);
+
+    commands.insert_resource(Explored(vec![vec![false; width]; height]));
+    markers.0.clear();
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(16.0),
+                top: Val::Px(16.0),
+                width: Val::Px(SMALL),
+                height: Val::Px(SMALL * height as f32 / width.max(1) as f32),
+                ..default()
+            },
+            background_color: Color::BLACK.into(),
+            ..default()
+        })
+        .insert(MinimapRoot)
+        .with_children(|parent| {
+            parent.spawn(ImageBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    ..default()
+                },
+                image: UiImage::new(image.clone()),
+                ..default()
+            });
+        });
+
+    commands.insert_resource(Minimap {
+        image,
+        enlarged: false,
+    });
+}
+
+/// Reveal the cells around the player whenever they enter a new one.
+pub fn explore_system(
+    game: Res<Game>,
+    players: Query<(), With<ControlledPlayer>>,
+    mut entered: EventReader<CellEntered>,
+    mut explored: ResMut<Explored>,
+) {
+    let Some((player_i, player_j)) = entered
+        .iter()
+        .filter(|entered| players.contains(entered.entity))
+        .last()
+        .map(|entered| entered.to)
+    else {
+        return;
+    };
+
+    let size = game.board_size();
+
+    let radius = EXPLORE_RADIUS;
+    let mut revealed = false;
+
+    let cells = explored.bypass_change_detection();
+    for j in player_j.saturating_sub(radius)..=(player_j + radius).min(size.1 - 1) {
+        for i in player_i.saturating_sub(radius)..=(player_i + radius).min(size.0 - 1) {
+            let distance = Vec2::new(i as f32 - player_i as f32, j as f32 - player_j as f32);
+            if distance.length() <= radius as f32 && !cells.0[j][i] {
+                cells.0[j][i] = true;
+                revealed = true;
+            }
+        }
+    }
+
+    if revealed {
+        explored.set_changed();
+    }
+}
+
+pub fn draw_minimap_system(
+    game: Res<Game>,
+    explored: Res<Explored>,
+    minimap: Option<Res<Minimap>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Some(minimap) = minimap else {
+        return;
+    };
+    let Some(image) = images.get_mut(&minimap.image) else {
+        return;
+    };
+
+    let heights = game.board.iter().flatten().map(|cell| cell.height);
+    let lowest = heights.clone().fold(f32::MAX, f32::min);
+    let highest = heights.fold(f32::MIN, f32::max);
+    let range = (highest - lowest).max(f32::EPSILON);
+
+    let width = game.board_size().0;
+    for (j, row) in game.board.iter().enumerate() {
+        for (i, cell) in row.iter().enumerate() {
+            let pixel = if explored.0[j][i] {
+                let shade = 0.3 + 0.5 * (cell.height - lowest) / range;
+                cell.kind.color().map_or_else(
+                    || Color::rgb(shade * 0.5, shade, shade * 0.5),
+                    |color| color.with_a(1.0),
+                )
+            } else {
+                FOG
+            };
+
+            let index = (j * width + i) * 4;
+            image.data[index..index + 4].copy_from_slice(&pixel.as_rgba_u8());
+        }
+    }
+}
+
+/// The board markers are placed on, and how much of it the player has seen.
+#[derive(SystemParam)]
+pub struct MinimapBoard<'w> {
+    game: Res<'w, Game>,
+    explored: Res<'w, Explored>,
+}
+
+/// `relation` is how the marked entity gets along with the player.
+fn marker_color(player: bool, relation: Option<Relation>, icon: Option<&MinimapIcon>) -> Color {
+    if player {
+        return Color::WHITE;
+    }
+
+    match (relation, icon) {
+        (_, Some(MinimapIcon::Pickup)) => Color::YELLOW,
+        (_, Some(MinimapIcon::Exit)) => Color::CYAN,
+        (Some(Relation::Friendly), _) => Color::GREEN,
+        (Some(Relation::Neutral), _) => Color::GRAY,
+        _ => Color::RED,
+    }
+}
+
+pub fn minimap_marker_system(
+    mut commands: Commands,
+    board: MinimapBoard,
+    relations: Res<FactionRelations>,
+    mut markers: ResMut<MinimapMarkers>,
+    roots: Query<Entity, With<MinimapRoot>>,
+    tracked: Query<
+        (
+            Entity,
+            &Transform,
+            Option<&Faction>,
+            Option<&MinimapIcon>,
+            Option<&ControlledPlayer>,
+        ),
+        Or<(With<Faction>, With<MinimapIcon>)>,
+    >,
+    mut marker_nodes: Query<(&mut Style, &mut Visibility), With<MinimapMarker>>,
+) {
+    let Ok(root) = roots.get_single() else {
+        return;
+    };
+
+    let size = board.game.board_size();
+    let player_faction = tracked
+        .iter()
+        .find_map(|(_, _, faction, _, player)| player.and(faction))
+        .copied()
+        .unwrap_or(Faction::PLAYER);
+
+    markers.0.retain(|entity, marker| {
+        let alive = tracked.contains(*entity);
+        if !alive {
+            commands.entity(*marker).despawn_recursive();
+        }
+        alive
+    });
+
+    for (entity, transform, faction, icon, player) in &tracked {
+        let cell = board.game.world_to_cell(transform.translation);
+        let visible = player.is_some() || cell.is_some_and(|(i, j)| board.explored.0[j][i]);
+
+        let left = Val::Percent(100.0 * (transform.translation.x + 0.5) / size.0 as f32);
+        let top = Val::Percent(100.0 * (transform.translation.z + 0.5) / size.1 as f32);
+
+        let Some(marker) = markers.0.get(&entity) else {
+            let marker = commands
+                .spawn(NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        left,
+                        top,
+                        width: Val::Px(MARKER_SIZE),
+                        height: Val::Px(MARKER_SIZE),
+                        ..default()
+                    },
+                    background_color: marker_color(
+                        player.is_some(),
+                        faction.map(|faction| relations.get(player_faction, *faction)),
+                        icon,
+                    )
+                    .into(),
+                    ..default()
+                })
+                .insert(MinimapMarker)
+                .id();
+            commands.entity(root).add_child(marker);
+            markers.0.insert(entity, marker);
+            continue;
+        };
+
+        let Ok((mut style, mut visibility)) = marker_nodes.get_mut(*marker) else {
+            continue;
+        };
+
+        style.left = left;
+        style.top = top;
+        *visibility = if visible && cell.is_some() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+pub fn toggle_minimap_system(
+    keyboard: Res<Input<KeyCode>>,
+    game: Res<Game>,
+    minimap: Option<ResMut<Minimap>>,
+    mut roots: Query<&mut Style, With<MinimapRoot>>,
+) {
+    let Some(mut minimap) = minimap else {
+        return;
+    };
+
+    if !keyboard.just_pressed(KeyCode::M) {
+        return;
+    }
+
+    minimap.enlarged = !minimap.enlarged;
+    let width = if minimap.enlarged { LARGE } else { SMALL };
+    let (columns, rows) = game.board_size();
+
+    for mut style in &mut roots {
+        style.width = Val::Px(width);
+        style.height = Val::Px(width * rows as f32 / columns.max(1) as f32);
+    }
+}
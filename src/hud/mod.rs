@@ -0,0 +1,296 @@
+//! The player's heads-up display.
+//!
+//! Shows the `ControlledPlayer`'s health, ability cooldowns and active
+//! conditions, how the encounter's waves are going, and the seed of the
+//! current board. A boss gets its own bar at the top of the screen. The
+//! systems only touch the UI when the player's components changed or were
+//! removed.
+
+use bevy::prelude::*;
+use std::marker::PhantomData;
+
+pub mod boss_bar;
+pub use boss_bar::*;
+
+pub mod damage_numbers;
+pub use damage_numbers::*;
+
+pub mod minimap;
+pub use minimap::*;
+
+use crate::{
+    actions::{
+        conditions::{Burning, Condition, ConditionName, Locked, Slowed},
+        JumpState,
+    },
+    agent::Health,
+    board::BoardSeed,
+    director::{Director, DirectorPhase},
+    ControlledPlayer, GameState, PauseState,
+};
+
+const READY: Color = Color::rgb(0.2, 0.6, 0.3);
+const COOLING_DOWN: Color = Color::rgb(0.3, 0.3, 0.3);
+
+#[derive(Component)]
+pub struct HealthFill;
+
+#[derive(Component)]
+pub struct HealthText;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Ability {
+    Attack,
+    Jump,
+}
+
+#[derive(Component)]
+pub struct CooldownIndicator(pub Ability);
+
+#[derive(Component)]
+pub struct WaveText;
+
+/// The row that condition icons are added to.
+#[derive(Component)]
+pub struct ConditionRow;
+
+#[derive(Component)]
+pub struct ConditionIcon<T>(PhantomData<T>);
+
+#[derive(Default)]
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((BossBarPlugin, DamageNumbersPlugin, MinimapPlugin))
+            .add_systems(OnEnter(GameState::Playing), spawn_hud)
+            .add_systems(
+                Update,
+                (
+                    hud_health_system,
+                    hud_cooldown_system,
+                    hud_wave_system,
+                    hud_condition_system::<Locked>,
+                    hud_condition_system::<Slowed>,
+                    hud_condition_system::<Burning>,
+                )
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(PauseState::Running)),
+            );
+    }
+}
+
+fn text_style(font_size: f32) -> TextStyle {
+    TextStyle {
+        font_size,
+        color: Color::WHITE,
+        ..default()
+    }
+}
+
+fn spawn_hud(mut commands: Commands, seed: Res<BoardSeed>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(16.0),
+                top: Val::Px(16.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|hud| {
+            // Health
+            hud.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Px(240.0),
+                    height: Val::Px(20.0),
+                    ..default()
+                },
+                background_color: Color::rgb(0.2, 0.05, 0.05).into(),
+                ..default()
+            })
+            .with_children(|track| {
+                track
+                    .spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Percent(100.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.8, 0.1, 0.1).into(),
+                        ..default()
+                    })
+                    .insert(HealthFill);
+                track
+                    .spawn(
+                        TextBundle::from_section("", text_style(16.0)).with_style(Style {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(6.0),
+                            ..default()
+                        }),
+                    )
+                    .insert(HealthText);
+            });
+
+            // Cooldowns
+            hud.spawn(NodeBundle {
+                style: Style {
+                    column_gap: Val::Px(6.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|row| {
+                for (ability, label) in [(Ability::Attack, "Attack"), (Ability::Jump, "Jump")] {
+                    row.spawn(NodeBundle {
+                        style: Style {
+                            width: Val::Px(64.0),
+                            height: Val::Px(24.0),
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        background_color: READY.into(),
+                        ..default()
+                    })
+                    .insert(CooldownIndicator(ability))
+                    .with_children(|indicator| {
+                        indicator.spawn(TextBundle::from_section(label, text_style(14.0)));
+                    });
+                }
+            });
+
+            // Conditions
+            hud.spawn(NodeBundle {
+                style: Style {
+                    column_gap: Val::Px(6.0),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(ConditionRow);
+
+            hud.spawn(TextBundle::from_section("", text_style(18.0)))
+                .insert(WaveText);
+
+            hud.spawn(TextBundle::from_section(
+                format!("Seed: {}", seed.0),
+                text_style(14.0),
+            ));
+        });
+}
+
+pub fn hud_health_system(
+    players: Query<&Health, (With<ControlledPlayer>, Changed<Health>)>,
+    mut fills: Query<&mut Style, With<HealthFill>>,
+    mut texts: Query<&mut Text, With<HealthText>>,
+) {
+    let Ok(health) = players.get_single() else {
+        return;
+    };
+
+    for mut style in &mut fills {
+        style.width = Val::Percent(health.ratio() * 100.0);
+    }
+
+    for mut text in &mut texts {
+        text.sections[0].value = format!("{:.0} / {:.0}", health.current.max(0.0), health.max);
+    }
+}
+
+pub fn hud_wave_system(director: Res<Director>, mut texts: Query<&mut Text, With<WaveText>>) {
+    if !director.is_changed() {
+        return;
+    }
+
+    let label = match director.phase {
+        DirectorPhase::Starting | DirectorPhase::Done => String::new(),
+        DirectorPhase::Break => format!(
+            "Wave {} of {} in {:.0}s",
+            director.wave + 1,
+            director.total,
+            director.timer.remaining_secs().ceil()
+        ),
+        DirectorPhase::Fighting => format!(
+            "Wave {} of {}: {} left",
+            director.wave, director.total, director.alive
+        ),
+    };
+
+    for mut text in &mut texts {
+        if text.sections[0].value != label {
+            text.sections[0].value.clone_from(&label);
+        }
+    }
+}
+
+pub fn hud_cooldown_system(
+    players: Query<
+        (Entity, Option<Ref<Condition<Locked>>>, Ref<JumpState>),
+        With<ControlledPlayer>,
+    >,
+    mut unlocked: RemovedComponents<Condition<Locked>>,
+    mut indicators: Query<(&CooldownIndicator, &mut BackgroundColor)>,
+) {
+    let Ok((player, locked, jump_state)) = players.get_single() else {
+        return;
+    };
+
+    let unlocked = unlocked.iter().any(|entity| entity == player);
+    let locked_changed = locked.as_ref().is_some_and(DetectChanges::is_changed);
+
+    if !(unlocked || locked_changed || jump_state.is_changed()) {
+        return;
+    }
+
+    for (CooldownIndicator(ability), mut color) in &mut indicators {
+        let ready = match ability {
+            Ability::Attack => locked.is_none(),
+            Ability::Jump => jump_state.available(),
+        };
+
+        let new_color: BackgroundColor = if ready { READY } else { COOLING_DOWN }.into();
+        if color.0 != new_color.0 {
+            *color = new_color;
+        }
+    }
+}
+
+pub fn hud_condition_system<T: ConditionName + Send + Sync + 'static>(
+    mut commands: Commands,
+    players: Query<&Condition<T>, (With<ControlledPlayer>, Changed<Condition<T>>)>,
+    mut removed: RemovedComponents<Condition<T>>,
+    controlled: Query<(), With<ControlledPlayer>>,
+    rows: Query<Entity, With<ConditionRow>>,
+    mut icons: Query<(Entity, &mut Text), With<ConditionIcon<T>>>,
+) {
+    if removed.iter().any(|entity| controlled.contains(entity)) {
+        for (icon, _) in &icons {
+            commands.entity(icon).despawn_recursive();
+        }
+        return;
+    }
+
+    let Ok(condition) = players.get_single() else {
+        return;
+    };
+
+    let label = format!("{} {:.1}s", T::NAME, condition.remaining().as_secs_f32());
+
+    if let Ok((_, mut text)) = icons.get_single_mut() {
+        text.sections[0].value = label;
+        return;
+    }
+
+    let Ok(row) = rows.get_single() else {
+        return;
+    };
+
+    commands.entity(row).with_children(|row| {
+        row.spawn(TextBundle::from_section(label, text_style(14.0)))
+            .insert(ConditionIcon::<T>(PhantomData));
+    });
+}
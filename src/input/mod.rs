@@ -1,10 +1,10 @@
 use crate::actions::Action;
+use crate::menu::Restart;
+use crate::save::Save;
 use crate::{Game, GameState};
-use bevy::input::mouse::MouseButtonInput;
 use bevy::math::Vec3Swizzles;
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
-use leafwing_input_manager::action_state::ActionData;
 use leafwing_input_manager::axislike::DualAxisData;
 use leafwing_input_manager::prelude::ActionState;
 
@@ -42,60 +42,50 @@ pub fn cursor_system(
     }
 }
 
-pub fn mouse_button_events(
+/// Aim the player's attack at the cursor.
+///
+/// `Action::Attack` is bound to the left mouse button, so leafwing tracks when
+/// it is pressed, held and released. This only fills in where it points.
+pub fn aim_system(
     camera: Query<(&Camera, &GlobalTransform)>,
     q_windows: Query<&Window, With<PrimaryWindow>>,
-    mut mousebtn_evr: EventReader<MouseButtonInput>,
     mut player_query: Query<(&mut ActionState<Action>, &Transform)>,
-
-    game: ResMut<Game>,
+    game: Res<Game>,
 ) {
-    use bevy::input::ButtonState;
-
-    for ev in &mut mousebtn_evr {
-        let Some(player) = game.player.entity else {
-            return;
-        };
-
-        let Ok((mut action_state, transform)) = player_query.get_mut(player) else {
-            return;
-        };
+    let Some(player) = game.player.entity else {
+        return;
+    };
 
-        let (camera, camera_transform) = camera.single();
-        if ev.state == ButtonState::Pressed {
-            let Some(position) = q_windows.single().cursor_position() else {
-                continue;
-            };
+    let Ok((mut action_state, transform)) = player_query.get_mut(player) else {
+        return;
+    };
 
-            if let Some(point) = camera.viewport_to_world(camera_transform, position) {
-                let t = -point.origin.y / point.direction.y;
-                let intersection = point.origin + t * point.direction + Vec3::Y * 0.1;
+    let Some(position) = q_windows.single().cursor_position() else {
+        return;
+    };
 
-                let mut intersection = intersection;
-                intersection.y = transform.translation.y;
+    let (camera, camera_transform) = camera.single();
+    if let Some(point) = camera.viewport_to_world(camera_transform, position) {
+        let t = -point.origin.y / point.direction.y;
+        let intersection = point.origin + t * point.direction;
 
-                let vector = intersection.xz() - transform.translation.xz();
+        let vector = intersection.xz() - transform.translation.xz();
 
-                action_state.set_action_data(
-                    Action::Attack,
-                    ActionData {
-                        state: leafwing_input_manager::buttonlike::ButtonState::Pressed,
-                        value: 1.,
-                        axis_pair: Some(DualAxisData::from_xy(vector)),
-                        ..Default::default()
-                    },
-                );
-            }
-        }
+        let mut data = action_state.action_data(Action::Attack).clone();
+        data.axis_pair = Some(DualAxisData::from_xy(vector));
+        action_state.set_action_data(Action::Attack, data);
     }
 }
 
 // restart the game when pressing spacebar
 pub fn gameover_keyboard(
+    mut commands: Commands,
     mut next_state: ResMut<NextState<GameState>>,
     keyboard_input: Res<Input<KeyCode>>,
 ) {
     if keyboard_input.just_pressed(KeyCode::Space) {
-        next_state.set(GameState::Playing);
+        commands.remove_resource::<Save>();
+        commands.insert_resource(Restart);
+        next_state.set(GameState::LoadingGame);
     }
 }
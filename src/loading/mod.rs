@@ -0,0 +1,121 @@
+//! The loading screen shown during `GameState::LoadingGame`.
+//!
+//! It draws whatever `ProgressCounter` holds, so besides asset loading any
+//! system can report into it by returning a `Progress` and being added with
+//! `.track_progress()` while in `LoadingGame`. See `board::generate_board`.
+
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+use iyes_progress::prelude::*;
+
+use crate::GameState;
+
+#[derive(Component)]
+pub struct LoadingScreen;
+
+#[derive(Component)]
+pub struct LoadingBar;
+
+#[derive(Component)]
+pub struct LoadingText;
+
+#[derive(Default)]
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::LoadingGame), spawn_loading_screen)
+            .add_systems(OnExit(GameState::LoadingGame), despawn_loading_screen)
+            .add_systems(
+                PostUpdate,
+                loading_screen_system
+                    .before(UiSystem::Layout)
+                    .run_if(in_state(GameState::LoadingGame)),
+            );
+    }
+}
+
+fn spawn_loading_screen(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            background_color: Color::BLACK.into(),
+            ..default()
+        })
+        .insert(LoadingScreen)
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        width: Val::Px(400.0),
+                        height: Val::Px(16.0),
+                        ..default()
+                    },
+                    background_color: Color::DARK_GRAY.into(),
+                    ..default()
+                })
+                .with_children(|track| {
+                    track
+                        .spawn(NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: Color::ANTIQUE_WHITE.into(),
+                            ..default()
+                        })
+                        .insert(LoadingBar);
+                });
+
+            parent
+                .spawn(TextBundle::from_section(
+                    "0%",
+                    TextStyle {
+                        font_size: 24.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ))
+                .insert(LoadingText);
+        });
+}
+
+fn despawn_loading_screen(mut commands: Commands, screens: Query<Entity, With<LoadingScreen>>) {
+    for entity in &screens {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn loading_screen_system(
+    counter: Option<Res<ProgressCounter>>,
+    mut bars: Query<&mut Style, With<LoadingBar>>,
+    mut texts: Query<&mut Text, With<LoadingText>>,
+) {
+    let Some(counter) = counter else {
+        return;
+    };
+
+    let progress = counter.progress();
+    let ratio = if progress.total == 0 {
+        0.0
+    } else {
+        progress.done as f32 / progress.total as f32
+    };
+
+    for mut style in &mut bars {
+        style.width = Val::Percent(ratio * 100.0);
+    }
+
+    for mut text in &mut texts {
+        text.sections[0].value = format!("{:.0}%", ratio * 100.0);
+    }
+}
@@ -7,6 +7,7 @@
     clippy::wildcard_imports
 )]
 
+use bevy::ecs::schedule::Condition as _;
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use iyes_progress::prelude::*;
@@ -33,19 +34,40 @@ use animation::*;
 mod setup;
 use setup::*;
 
+mod board;
+use board::*;
+
+mod menu;
+use menu::*;
+
+mod save;
+use save::*;
+
+mod loading;
+use loading::*;
+
+mod hud;
+use hud::*;
+
+mod faction;
+use faction::*;
+
+mod director;
+use director::*;
+
 mod utils;
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
 pub enum GameState {
     #[default]
     LoadingGame,
+    MainMenu,
     Playing,
     GameOver,
+    /// Every wave of the encounter was cleared.
+    Victory,
 }
 
-#[derive(Component, Clone, Default)]
-pub struct Faction(u32);
-
 fn main() {
     App::new()
         .add_state::<GameState>()
@@ -54,13 +76,23 @@ fn main() {
         .add_plugins(RapierDebugRenderPlugin::default())
         .add_plugins(InputManagerPlugin::<Action>::default())
         .add_plugins(MobPlugin)
+        .add_plugins(HealthPlugin)
+        .add_plugins(MenuPlugin)
+        .add_plugins(LoadingPlugin)
+        .add_plugins(HudPlugin)
+        .add_plugins(BoardPlugin)
+        .add_plugins(DirectorPlugin)
+        .add_plugins(SavePlugin)
         .add_plugins(
             ProgressPlugin::new(GameState::LoadingGame)
-                .continue_to(GameState::Playing)
+                .continue_to(GameState::MainMenu)
                 .track_assets(),
         )
         .insert_resource(RapierConfiguration::default())
         .init_resource::<Game>()
+        .init_resource::<FactionRelations>()
+        .init_resource::<CameraFocus>()
+        .add_event::<attack::Hit>()
         .add_systems(OnEnter(GameState::LoadingGame), load_gltf)
         .add_systems(Startup, setup_cameras)
         .add_systems(OnEnter(GameState::Playing), (setup, setup_scene, spawn_mob))
@@ -70,37 +102,41 @@ fn main() {
                 animate_upon_load,
                 move_system,
                 camera::focus_system,
-                mouse_button_events.before(attack_system),
+                aim_system.before(attack_system),
                 attack_system,
+                Charging::indicator_system.after(attack_system),
                 jump,
                 gravity_system,
                 cursor_system,
                 Condition::<Condition<Locked>>::system,
+                Condition::<Condition<Slowed>>::system,
+                Condition::<Condition<Burning>>::system,
                 ActiveAnimation::queue_system,
                 attack::LifeSpan::system,
                 attack::PendingAttack::system,
+                attack::Attack::hit_system,
+                attack::Attack::projectile_system.after(attack::Attack::hit_system),
+                attack::Knockback::system,
             )
-                .run_if(in_state(GameState::Playing)),
+                .run_if(in_state(GameState::Playing))
+                .run_if(in_state(PauseState::Running)),
         )
         .add_systems(OnExit(GameState::Playing), teardown)
         .add_systems(
             Update,
-            (
-                gameover_keyboard.run_if(in_state(GameState::GameOver)),
-                bevy::window::close_on_esc,
-            ),
+            gameover_keyboard
+                .run_if(in_state(GameState::GameOver).or_else(in_state(GameState::Victory))),
         )
         .add_systems(OnExit(GameState::GameOver), teardown)
+        .add_systems(OnExit(GameState::Victory), teardown)
         .run();
 }
 
-struct Cell {
-    height: f32,
-}
-
 #[derive(Default)]
 struct Player {
     entity: Option<Entity>,
+    /// The last cell the player stood on safely, to put them back on after a fall.
+    safe: (usize, usize),
     i: usize,
     j: usize,
 }
@@ -111,6 +147,11 @@ pub struct ControlledPlayer;
 #[derive(Resource, Default)]
 pub struct Game {
     board: Vec<Vec<Cell>>,
+    player_spawn: (usize, usize),
+    mob_spawns: Vec<MobSpawn>,
+    /// Cells the encounter director spawns waves at.
+    spawn_points: Vec<(usize, usize)>,
+    props: Vec<Prop>,
     player: Player,
     camera_should_focus: Vec3,
     camera_is_focus: Vec3,
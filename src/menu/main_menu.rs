@@ -0,0 +1,88 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+
+use super::{despawn_menu, spawn_menu, MenuFocus, MenuPressed, MenuState};
+use crate::{save::Save, GameState};
+
+#[derive(Component, Clone, Copy)]
+pub enum MainMenuButton {
+    NewGame,
+    Continue,
+    Settings,
+    Quit,
+}
+
+/// Skip the main menu once and go straight into a new run.
+#[derive(Resource)]
+pub struct Restart;
+
+#[derive(Default)]
+pub struct MainMenuPlugin;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::MainMenu), enter_main_menu)
+            .add_systems(OnEnter(MenuState::Main), spawn_main_menu)
+            .add_systems(OnExit(MenuState::Main), despawn_menu)
+            .add_systems(Update, main_menu_system.run_if(in_state(MenuState::Main)));
+    }
+}
+
+fn enter_main_menu(
+    mut commands: Commands,
+    restart: Option<Res<Restart>>,
+    mut next_game: ResMut<NextState<GameState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    if restart.is_some() {
+        commands.remove_resource::<Restart>();
+        next_game.set(GameState::Playing);
+    } else {
+        next_menu.set(MenuState::Main);
+    }
+}
+
+fn spawn_main_menu(mut commands: Commands, mut focus: ResMut<MenuFocus>) {
+    let mut items = vec![("New Game", MainMenuButton::NewGame)];
+    if Save::exists() {
+        items.push(("Continue", MainMenuButton::Continue));
+    }
+    items.push(("Settings", MainMenuButton::Settings));
+    items.push(("Quit", MainMenuButton::Quit));
+
+    spawn_menu(&mut commands, &mut focus, "Bevy Game", &items);
+}
+
+pub fn main_menu_system(
+    mut commands: Commands,
+    mut pressed: EventReader<MenuPressed>,
+    buttons: Query<&MainMenuButton>,
+    mut next_game: ResMut<NextState<GameState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for MenuPressed(entity) in &mut pressed {
+        let Ok(button) = buttons.get(*entity) else {
+            continue;
+        };
+
+        match button {
+            MainMenuButton::NewGame => {
+                commands.remove_resource::<Save>();
+                next_menu.set(MenuState::Closed);
+                next_game.set(GameState::Playing);
+            }
+            MainMenuButton::Continue => {
+                // The save's board has to be generated again from its seed.
+                if let Some(save) = Save::load() {
+                    commands.insert_resource(save);
+                }
+                commands.insert_resource(Restart);
+                next_menu.set(MenuState::Closed);
+                next_game.set(GameState::LoadingGame);
+            }
+            MainMenuButton::Settings => next_menu.set(MenuState::Settings),
+            MainMenuButton::Quit => exit.send(AppExit),
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! Menus are full-screen `bevy_ui` pages made of a column of buttons.
+//!
+//! Every button has a `MenuButton` with its position in the column, plus a
+//! page-specific component saying what it does. `navigation_system` moves the
+//! focus with the keyboard, mouse or gamepad and fires a `MenuPressed` event
+//! for the button that was activated. Each page reads those events and looks
+//! up its own component on the pressed entity.
+
+use bevy::prelude::*;
+
+pub mod main_menu;
+pub use main_menu::*;
+
+pub mod pause;
+pub use pause::*;
+
+pub mod settings;
+pub use settings::*;
+
+const BACKGROUND: Color = Color::rgba(0.0, 0.0, 0.0, 0.6);
+const BUTTON: Color = Color::rgb(0.15, 0.15, 0.15);
+const BUTTON_FOCUSED: Color = Color::rgb(0.35, 0.35, 0.55);
+
+/// Which menu page is open.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
+pub enum MenuState {
+    #[default]
+    Closed,
+    Main,
+    Pause,
+    Settings,
+}
+
+#[derive(Component)]
+pub struct MenuRoot;
+
+#[derive(Component)]
+pub struct MenuButton {
+    pub index: usize,
+}
+
+/// Index of the focused button on the open page.
+#[derive(Resource, Default)]
+pub struct MenuFocus(pub usize);
+
+#[derive(Event)]
+pub struct MenuPressed(pub Entity);
+
+#[derive(Default)]
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<MenuState>()
+            .init_resource::<MenuFocus>()
+            .add_event::<MenuPressed>()
+            .add_plugins((MainMenuPlugin, PausePlugin, SettingsPlugin))
+            .add_systems(Update, navigation_system);
+    }
+}
+
+/// Spawn a page with a title and one button per item.
+pub fn spawn_menu<T: Component + Copy>(
+    commands: &mut Commands,
+    focus: &mut MenuFocus,
+    title: &str,
+    items: &[(&str, T)],
+) -> Entity {
+    focus.0 = 0;
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                row_gap: Val::Px(12.0),
+                ..default()
+            },
+            background_color: BACKGROUND.into(),
+            ..default()
+        })
+        .insert(MenuRoot)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                title,
+                TextStyle {
+                    font_size: 48.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+
+            for (index, (label, item)) in items.iter().enumerate() {
+                parent
+                    .spawn(ButtonBundle {
+                        style: Style {
+                            width: Val::Px(240.0),
+                            height: Val::Px(48.0),
+                            align_items: AlignItems::Center,
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        },
+                        background_color: BUTTON.into(),
+                        ..default()
+                    })
+                    .insert(MenuButton { index })
+                    .insert(*item)
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            *label,
+                            TextStyle {
+                                font_size: 24.0,
+                                color: Color::WHITE,
+                                ..default()
+                            },
+                        ));
+                    });
+            }
+        })
+        .id()
+}
+
+pub fn despawn_menu(mut commands: Commands, roots: Query<Entity, With<MenuRoot>>) {
+    for entity in &roots {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn any_gamepad_just_pressed(
+    gamepads: &Gamepads,
+    buttons: &Input<GamepadButton>,
+    button_type: GamepadButtonType,
+) -> bool {
+    gamepads
+        .iter()
+        .any(|gamepad| buttons.just_pressed(GamepadButton::new(gamepad, button_type)))
+}
+
+pub fn navigation_system(
+    keyboard: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    mut focus: ResMut<MenuFocus>,
+    mut buttons: Query<(Entity, &MenuButton, Ref<Interaction>, &mut BackgroundColor)>,
+    mut pressed: EventWriter<MenuPressed>,
+) {
+    let count = buttons.iter().count();
+    if count == 0 {
+        return;
+    }
+
+    let just_pressed = |keys: [KeyCode; 2], button_type| {
+        keyboard.any_just_pressed(keys)
+            || any_gamepad_just_pressed(&gamepads, &gamepad_buttons, button_type)
+    };
+
+    if just_pressed([KeyCode::Up, KeyCode::W], GamepadButtonType::DPadUp) {
+        focus.0 = (focus.0 + count - 1) % count;
+    }
+    if just_pressed([KeyCode::Down, KeyCode::S], GamepadButtonType::DPadDown) {
+        focus.0 = (focus.0 + 1) % count;
+    }
+    focus.0 = focus.0.min(count - 1);
+
+    let confirm = just_pressed([KeyCode::Return, KeyCode::Space], GamepadButtonType::South);
+
+    for (entity, button, interaction, mut color) in &mut buttons {
+        match *interaction {
+            Interaction::Pressed if interaction.is_changed() => {
+                focus.0 = button.index;
+                pressed.send(MenuPressed(entity));
+            }
+            Interaction::Hovered => focus.0 = button.index,
+            _ => {}
+        }
+
+        if confirm && button.index == focus.0 {
+            pressed.send(MenuPressed(entity));
+        }
+
+        *color = if button.index == focus.0 {
+            BUTTON_FOCUSED.into()
+        } else {
+            BUTTON.into()
+        };
+    }
+}
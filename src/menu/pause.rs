@@ -0,0 +1,141 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use super::{
+    any_gamepad_just_pressed, despawn_menu, spawn_menu, MenuFocus, MenuPressed, MenuState, Restart,
+};
+use crate::{actions::Action, save::Save, GameState};
+
+/// Whether gameplay is frozen. Gameplay systems run only while `Running`.
+///
+/// This is separate from `GameState` because leaving `GameState::Playing`
+/// tears the level down.
+#[derive(Clone, Eq, PartialEq, Debug, Hash, Default, States)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+#[derive(Component, Clone, Copy)]
+pub enum PauseButton {
+    Resume,
+    Settings,
+    Restart,
+    Quit,
+}
+
+#[derive(Default)]
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_state::<PauseState>()
+            .add_systems(OnEnter(PauseState::Paused), freeze)
+            .add_systems(OnExit(PauseState::Paused), unfreeze)
+            .add_systems(OnEnter(MenuState::Pause), spawn_pause_menu)
+            .add_systems(OnExit(MenuState::Pause), despawn_menu)
+            .add_systems(
+                Update,
+                (toggle_pause_system, pause_menu_system).run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), resume);
+    }
+}
+
+pub fn toggle_pause_system(
+    keyboard: Res<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+    pause: Res<State<PauseState>>,
+    mut next_pause: ResMut<NextState<PauseState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    let toggle = keyboard.just_pressed(KeyCode::Escape)
+        || any_gamepad_just_pressed(&gamepads, &gamepad_buttons, GamepadButtonType::Start);
+
+    if !toggle {
+        return;
+    }
+
+    match pause.get() {
+        PauseState::Running => {
+            next_pause.set(PauseState::Paused);
+            next_menu.set(MenuState::Pause);
+        }
+        PauseState::Paused => {
+            next_pause.set(PauseState::Running);
+            next_menu.set(MenuState::Closed);
+        }
+    }
+}
+
+fn spawn_pause_menu(mut commands: Commands, mut focus: ResMut<MenuFocus>) {
+    spawn_menu(
+        &mut commands,
+        &mut focus,
+        "Paused",
+        &[
+            ("Resume", PauseButton::Resume),
+            ("Settings", PauseButton::Settings),
+            ("Restart", PauseButton::Restart),
+            ("Quit", PauseButton::Quit),
+        ],
+    );
+}
+
+pub fn pause_menu_system(
+    mut commands: Commands,
+    mut pressed: EventReader<MenuPressed>,
+    buttons: Query<&PauseButton>,
+    mut next_pause: ResMut<NextState<PauseState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+    mut next_game: ResMut<NextState<GameState>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for MenuPressed(entity) in &mut pressed {
+        let Ok(button) = buttons.get(*entity) else {
+            continue;
+        };
+
+        match button {
+            PauseButton::Resume => {
+                next_pause.set(PauseState::Running);
+                next_menu.set(MenuState::Closed);
+            }
+            PauseButton::Settings => next_menu.set(MenuState::Settings),
+            PauseButton::Restart => {
+                // Leaving `Playing` tears the level down and loading builds a
+                // new board. `Restart` makes the main menu skip itself.
+                commands.remove_resource::<Save>();
+                commands.insert_resource(Restart);
+                next_menu.set(MenuState::Closed);
+                next_game.set(GameState::LoadingGame);
+            }
+            // Saved by `Save::leave_system`.
+            PauseButton::Quit => exit.send(AppExit),
+        }
+    }
+}
+
+fn freeze(
+    mut rapier: ResMut<RapierConfiguration>,
+    mut toggle_actions: ResMut<ToggleActions<Action>>,
+) {
+    rapier.physics_pipeline_active = false;
+    toggle_actions.enabled = false;
+}
+
+fn unfreeze(
+    mut rapier: ResMut<RapierConfiguration>,
+    mut toggle_actions: ResMut<ToggleActions<Action>>,
+) {
+    rapier.physics_pipeline_active = true;
+    toggle_actions.enabled = true;
+}
+
+fn resume(mut next_pause: ResMut<NextState<PauseState>>) {
+    next_pause.set(PauseState::Running);
+}
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+
+use super::{despawn_menu, spawn_menu, MenuFocus, MenuPressed, MenuState};
+use crate::GameState;
+
+#[derive(Component, Clone, Copy)]
+pub enum SettingsButton {
+    PhysicsDebug,
+    Back,
+}
+
+#[derive(Default)]
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(MenuState::Settings), spawn_settings_menu)
+            .add_systems(OnExit(MenuState::Settings), despawn_menu)
+            .add_systems(
+                Update,
+                settings_menu_system.run_if(in_state(MenuState::Settings)),
+            );
+    }
+}
+
+fn physics_debug_label(enabled: bool) -> String {
+    format!("Physics debug: {}", if enabled { "On" } else { "Off" })
+}
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    mut focus: ResMut<MenuFocus>,
+    debug_render: Res<DebugRenderContext>,
+) {
+    let physics_debug = physics_debug_label(debug_render.enabled);
+    spawn_menu(
+        &mut commands,
+        &mut focus,
+        "Settings",
+        &[
+            (physics_debug.as_str(), SettingsButton::PhysicsDebug),
+            ("Back", SettingsButton::Back),
+        ],
+    );
+}
+
+pub fn settings_menu_system(
+    mut pressed: EventReader<MenuPressed>,
+    buttons: Query<(&SettingsButton, &Children)>,
+    mut texts: Query<&mut Text>,
+    mut debug_render: ResMut<DebugRenderContext>,
+    game_state: Res<State<GameState>>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+) {
+    for MenuPressed(entity) in &mut pressed {
+        let Ok((button, children)) = buttons.get(*entity) else {
+            continue;
+        };
+
+        match button {
+            SettingsButton::PhysicsDebug => {
+                debug_render.enabled = !debug_render.enabled;
+                for child in children {
+                    if let Ok(mut text) = texts.get_mut(*child) {
+                        text.sections[0].value = physics_debug_label(debug_render.enabled);
+                    }
+                }
+            }
+            SettingsButton::Back => {
+                if *game_state.get() == GameState::MainMenu {
+                    next_menu.set(MenuState::Main);
+                } else {
+                    next_menu.set(MenuState::Pause);
+                }
+            }
+        }
+    }
+}
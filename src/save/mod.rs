@@ -0,0 +1,102 @@
+//! A single save slot, stored as RON next to the executable, or in local
+//! storage on the web.
+//!
+//! The run is saved whenever it is left: quitting, closing the window, or
+//! switching away from the game's tab. Dying or winning ends the run, so the
+//! save is deleted then, and there is nothing to continue.
+
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::window::WindowFocused;
+use serde::{Deserialize, Serialize};
+
+use crate::{board::BoardSeed, Game, GameState};
+
+mod storage;
+
+#[derive(Default)]
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::GameOver), Save::delete_system)
+            .add_systems(OnEnter(GameState::Victory), Save::delete_system)
+            // Last, to see the `AppExit` sent when the window closes.
+            .add_systems(
+                Last,
+                Save::leave_system.run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, Debug)]
+pub struct Save {
+    /// The seed the board was generated from.
+    pub seed: u64,
+    /// The board cell the player was standing on, as `(i, j)`.
+    pub player: (usize, usize),
+}
+
+impl Save {
+    pub fn exists() -> bool {
+        storage::read().is_some()
+    }
+
+    pub fn load() -> Option<Self> {
+        let contents = storage::read()?;
+        match ron::from_str(&contents) {
+            Ok(save) => Some(save),
+            Err(error) => {
+                warn!("Ignoring unreadable save: {error}");
+                None
+            }
+        }
+    }
+
+    pub fn write(&self) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(error) => {
+                error!("Could not serialize save: {error}");
+                return;
+            }
+        };
+
+        if let Err(error) = storage::write(&contents) {
+            error!("Could not write save: {error}");
+        }
+    }
+
+    pub fn delete() {
+        if let Err(error) = storage::delete() {
+            error!("Could not delete save: {error}");
+        }
+    }
+
+    /// Snapshot the current run.
+    pub fn from_game(game: &Game, seed: BoardSeed) -> Self {
+        Self {
+            seed: seed.0,
+            player: game.player.safe,
+        }
+    }
+
+    pub fn delete_system() {
+        Save::delete();
+    }
+
+    /// Save when the game is quit, or its window loses focus. On the web
+    /// that's the last chance before the tab is closed.
+    pub fn leave_system(
+        mut exits: EventReader<AppExit>,
+        mut focus: EventReader<WindowFocused>,
+        game: Res<Game>,
+        seed: Res<BoardSeed>,
+    ) {
+        let exiting = exits.iter().count() > 0;
+        let unfocused = focus.iter().any(|event| !event.focused);
+        if exiting || unfocused {
+            Save::from_game(&game, *seed).write();
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Where the save slot lives: a file next to the executable, or the
+//! browser's local storage on the web.
+
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_PATH: &str = "save.ron";
+
+#[cfg(target_arch = "wasm32")]
+const SAVE_KEY: &str = "bevy_game.save";
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read() -> Option<String> {
+    std::fs::read_to_string(SAVE_PATH).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(contents: &str) -> Result<(), String> {
+    std::fs::write(SAVE_PATH, contents).map_err(|error| error.to_string())
+}
+
+/// Delete the save, if there is one.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn delete() -> Result<(), String> {
+    match std::fs::remove_file(SAVE_PATH) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read() -> Option<String> {
+    local_storage()?.get_item(SAVE_KEY).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(contents: &str) -> Result<(), String> {
+    local_storage()
+        .ok_or("no local storage")?
+        .set_item(SAVE_KEY, contents)
+        .map_err(|error| format!("{error:?}"))
+}
+
+/// Delete the save, if there is one.
+#[cfg(target_arch = "wasm32")]
+pub fn delete() -> Result<(), String> {
+    local_storage()
+        .ok_or("no local storage")?
+        .remove_item(SAVE_KEY)
+        .map_err(|error| format!("{error:?}"))
+}
@@ -2,20 +2,21 @@ use bevy::gltf::Gltf;
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use iyes_progress::prelude::AssetsLoading;
-use rand::Rng;
 use std::f32::consts::PI;
 
 use crate::animation::ActiveAnimation;
 use crate::{
-    actions::{Action, JumpState},
+    actions::{Action, JumpState, Momentum},
+    agent::Health,
     Animations, Faction,
 };
-use crate::{Cell, ControlledPlayer, Game};
+use crate::{
+    board::{board_collider, board_columns, chunk_meshes, SURFACES},
+    save::Save,
+    ControlledPlayer, Game,
+};
 use leafwing_input_manager::prelude::*;
 
-const BOARD_SIZE_I: usize = 14;
-const BOARD_SIZE_J: usize = 21;
-
 #[derive(Resource)]
 pub struct Assets3D(pub Handle<Gltf>);
 
@@ -29,7 +30,13 @@ pub fn load_gltf(
     commands.insert_resource(Assets3D(gltf));
 }
 
-pub fn setup_scene(mut commands: Commands, asset_server: Res<AssetServer>, mut game: ResMut<Game>) {
+pub fn setup_scene(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    game: Res<Game>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
     commands.spawn(PointLightBundle {
         transform: Transform::from_xyz(4.0, 10.0, 4.0),
         point_light: PointLight {
@@ -41,27 +48,41 @@ pub fn setup_scene(mut commands: Commands, asset_server: Res<AssetServer>, mut g
         ..default()
     });
 
-    // spawn the game board
-    let cell_scene = asset_server.load("models/world/tile.glb#Scene0");
-    game.board = (0..BOARD_SIZE_J)
-        .map(|j| {
-            (0..BOARD_SIZE_I)
-                .map(|i| {
-                    let height = rand::thread_rng().gen_range(-0.1..0.1);
-                    let transform = Transform::from_xyz(i as f32, height, j as f32);
-                    commands
-                        .spawn(SceneBundle {
-                            transform,
-                            scene: cell_scene.clone(),
-                            ..default()
-                        })
-                        .insert(RigidBody::Fixed)
-                        .insert(Collider::cuboid(0.5, 0.2, 0.5));
-                    Cell { height }
-                })
-                .collect()
-        })
-        .collect();
+    // spawn the level's props
+    for prop in &game.props {
+        let (i, j) = prop.cell;
+        let Some(cell) = game.board.get(j).and_then(|row| row.get(i)) else {
+            warn!("Prop {} at {:?} is off the board", prop.scene, prop.cell);
+            continue;
+        };
+
+        let top = cell.column().map_or(cell.height, |column| column.top);
+        let mut spawned = commands.spawn(SceneBundle {
+            transform: Transform::from_xyz(i as f32, top, j as f32)
+                .with_rotation(Quat::from_rotation_y(prop.rotation.to_radians())),
+            scene: asset_server.load(prop.scene.as_str()),
+            ..default()
+        });
+        if let Some(icon) = prop.icon {
+            spawned.insert(icon);
+        }
+    }
+
+    // spawn the game board, one mesh per chunk and a single collider
+    let columns = board_columns(&game.board);
+    let surfaces = SURFACES.map(|kind| materials.add(kind.material()));
+
+    for (surface, mesh) in chunk_meshes(&columns) {
+        commands.spawn(PbrBundle {
+            mesh: meshes.add(mesh),
+            material: surfaces[surface].clone(),
+            ..default()
+        });
+    }
+
+    if let Some(collider) = board_collider(&columns) {
+        commands.spawn((TransformBundle::default(), RigidBody::Fixed, collider));
+    }
 }
 
 pub fn setup(
@@ -69,9 +90,14 @@ pub fn setup(
     mut game: ResMut<Game>,
     models: Res<Assets3D>,
     assets_gltf: Res<Assets<Gltf>>,
+    save: Option<Res<Save>>,
 ) {
-    game.player.i = BOARD_SIZE_I / 2;
-    game.player.j = BOARD_SIZE_J / 2;
+    let spawn = match save {
+        Some(save) if game.cell(save.player).is_some() => save.player,
+        _ => game.player_spawn,
+    };
+    (game.player.i, game.player.j) = spawn;
+    game.player.safe = spawn;
 
     info!(
         "Animations: {:?}",
@@ -97,10 +123,24 @@ pub fn setup(
         idle: named_animations["ivory_idle"].clone(),
         run: named_animations["ivory_run"].clone(),
         attack: named_animations["ivory_slash"].clone(),
+        // Not every model has a wind-up, so fall back to standing still.
+        charge: named_animations
+            .get("ivory_charge")
+            .unwrap_or(&named_animations["ivory_idle"])
+            .clone(),
     };
 
     let active_animation = ActiveAnimation::new(animations);
 
+    let mut input_map = InputMap::new([
+        (KeyCode::Space, Action::Jump),
+        (KeyCode::W, Action::MoveNorth),
+        (KeyCode::A, Action::MoveWest),
+        (KeyCode::S, Action::MoveSouth),
+        (KeyCode::D, Action::MoveEast),
+    ]);
+    input_map.insert(MouseButton::Left, Action::Attack);
+
     // spawn the game character
     let entity = commands
         .spawn_empty()
@@ -128,7 +168,9 @@ pub fn setup(
             ..default()
         })
         .insert(JumpState::from(30.))
-        .insert(Faction(0))
+        .insert(Momentum::default())
+        .insert(Faction::PLAYER)
+        .insert(Health::new(10.0))
         .insert(RigidBody::KinematicPositionBased)
         .insert(Collider::capsule(
             Vec3::new(0.0, 0.24, 0.0),
@@ -138,13 +180,7 @@ pub fn setup(
         .insert(ControlledPlayer)
         .insert(InputManagerBundle::<Action> {
             action_state: ActionState::default(),
-            input_map: InputMap::new([
-                (KeyCode::Space, Action::Jump),
-                (KeyCode::W, Action::MoveNorth),
-                (KeyCode::A, Action::MoveWest),
-                (KeyCode::S, Action::MoveSouth),
-                (KeyCode::D, Action::MoveEast),
-            ]),
+            input_map,
         })
         .id();
     game.player.entity = Some(entity);